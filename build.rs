@@ -1,12 +1,20 @@
+use std::env;
 use std::path::Path;
 
 fn main() {
     // Tell cargo to re-run build if the terminal-notifier.app changes
     println!("cargo:rerun-if-changed=resources/terminal-notifier.app");
 
+    // The app is only embedded when building for macOS with the bundle feature
+    let bundle_enabled = env::var_os("CARGO_FEATURE_MACOS_BUNDLE").is_some();
+    let targets_macos = env::var("CARGO_CFG_TARGET_OS").map(|os| os == "macos").unwrap_or(false);
+    if !bundle_enabled || !targets_macos {
+        return;
+    }
+
     // Verify that terminal-notifier.app exists at build time
     let app_path = Path::new("resources/terminal-notifier.app");
     if !app_path.exists() {
         panic!("terminal-notifier.app not found in resources directory. Please ensure it exists.");
     }
-}
\ No newline at end of file
+}
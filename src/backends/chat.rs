@@ -0,0 +1,212 @@
+//! What the Slack and Discord backends share: channels with their own event
+//! filter, colours by event, and the fields shown in every message. Telegram
+//! and Matrix use the fields and the escaping too.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::webhook::{self, payload_or_stub, RetryConfig};
+use super::DeliveryContext;
+use crate::http::Request;
+use crate::is_tool_failure;
+use crate::rules::glob_match;
+use crate::session_store::SessionStore;
+use crate::types::{event_config_key, NotificationData};
+
+/// Colour key for a tool call that failed, usable in `colors`
+const TOOL_FAILURE: &str = "tool_failure";
+
+/// An incoming-webhook integration, configured under `[backends.slack]` or
+/// `[backends.discord]`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ChatConfig {
+    #[serde(default)]
+    pub channels: Vec<ChatChannel>,
+    /// `#rrggbb` colours by event key or `tool_failure`, over the defaults
+    #[serde(default)]
+    pub colors: BTreeMap<String, String>,
+    #[serde(flatten)]
+    pub retry: RetryConfig,
+}
+
+/// A `[[backends.slack.channels]]` or `[[backends.discord.channels]]` entry
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatChannel {
+    /// The channel's incoming webhook URL
+    pub url: String,
+    /// Event names forwarded to this channel, as globs
+    #[serde(default = "default_events")]
+    pub events: Vec<String>,
+}
+
+/// The parts of a notification a chat message shows
+#[derive(Debug)]
+pub struct ChatMessage {
+    pub title: String,
+    pub body: String,
+    pub event: String,
+    /// `0xrrggbb`
+    pub color: u32,
+    pub project: Option<String>,
+    pub cwd: Option<String>,
+    pub session_id: Option<String>,
+    pub terminal_app: Option<String>,
+}
+
+impl ChatConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (key, color) in &self.colors {
+            parse_color(color).ok_or_else(|| format!("colors.{}: '{}' is not a #rrggbb colour", key, color))?;
+        }
+        Ok(())
+    }
+
+    fn color(&self, key: &str) -> u32 {
+        self.colors.get(key)
+            .and_then(|color| parse_color(color))
+            .unwrap_or_else(|| default_color(key))
+    }
+}
+
+impl ChatMessage {
+    pub fn new(data: &NotificationData, ctx: &DeliveryContext, config: &ChatConfig) -> Self {
+        let payload = payload_or_stub(ctx);
+        let session = ctx.session_id.and_then(|id| SessionStore::open().get_session(id));
+        let terminal = session.as_ref().map(|s| &s.terminal_info);
+
+        let cwd = payload.cwd.clone()
+            .or_else(|| session.as_ref().and_then(|s| s.cwd.clone()))
+            .or_else(|| terminal.and_then(|t| t.cwd.clone()));
+        let project = terminal.and_then(|t| t.project_dir.as_deref())
+            .or(cwd.as_deref())
+            .and_then(|dir| Path::new(dir).file_name())
+            .map(|name| name.to_string_lossy().to_string());
+
+        let color_key = if is_tool_failure(&payload) { TOOL_FAILURE.to_string() } else { event_config_key(ctx.event) };
+
+        ChatMessage {
+            title: data.title.clone(),
+            body: data.body.clone(),
+            event: ctx.event.to_string(),
+            color: config.color(&color_key),
+            project,
+            cwd,
+            session_id: ctx.session_id.map(String::from),
+            terminal_app: terminal.and_then(|t| t.terminal_app.clone()),
+        }
+    }
+
+    /// Label and value of each context field that is known
+    pub fn fields(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("Project", &self.project),
+            ("Directory", &self.cwd),
+            ("Session", &self.session_id),
+            ("Terminal", &self.terminal_app),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| value.as_deref().map(|value| (label, value)))
+        .collect()
+    }
+}
+
+/// Post the message rendered by `render` to every channel that forwards the
+/// event. Nothing is sent, and that's fine, if no channel wants it.
+pub fn post(
+    backend: &str,
+    config: &ChatConfig,
+    data: &NotificationData,
+    ctx: &DeliveryContext,
+    render: impl Fn(&ChatMessage) -> Value,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let channels: Vec<&ChatChannel> = config.channels.iter()
+        .filter(|channel| channel.events.iter().any(|pattern| glob_match(pattern, ctx.event)))
+        .collect();
+    if channels.is_empty() {
+        return Ok(());
+    }
+
+    let body = render(&ChatMessage::new(data, ctx, config));
+    let errors: Vec<String> = channels.iter()
+        .filter_map(|channel| {
+            let request = Request::new("POST", &channel.url)
+                .timeout(config.retry.timeout())
+                .json(&body);
+            webhook::deliver(&request, &config.retry, backend, ctx).err()
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; ").into())
+    }
+}
+
+/// Escape `&`, `<` and `>`, which is all Slack's mrkdwn needs and enough for
+/// text inside Telegram's and Matrix's HTML
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn parse_color(color: &str) -> Option<u32> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+fn default_color(key: &str) -> u32 {
+    match key {
+        "notification" => 0xecb22e,
+        "stop" | "subagent_stop" => 0x2eb67d,
+        TOOL_FAILURE => 0xe01e5a,
+        "pre_tool_use" | "post_tool_use" => 0x36c5f0,
+        _ => 0x9e9ea6,
+    }
+}
+
+fn default_events() -> Vec<String> {
+    vec!["Stop".to_string(), "Notification".to_string()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Config, HookPayload};
+    use serde_json::json;
+
+    #[test]
+    fn test_message_fields_and_colours() {
+        let config: ChatConfig = toml::from_str(r##"
+            colors = { stop = "#000001" }
+            [[channels]]
+            url = "http://localhost/hook"
+        "##).unwrap();
+        assert!(config.validate().is_ok());
+
+        let app = Config::default();
+        let data = NotificationData { title: "Claude Tool Failed".to_string(), body: "Write failed".to_string(), sound: "Basso".to_string() };
+        let payload: HookPayload = serde_json::from_value(json!({
+            "hook_event_name": "PostToolUse",
+            "cwd": "/work/repo",
+            "tool_name": "Write",
+            "tool_response": { "success": false }
+        })).unwrap();
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "PostToolUse", payload: Some(&payload), inline: false };
+
+        let message = ChatMessage::new(&data, &ctx, &config);
+        assert_eq!(message.color, 0xe01e5a);
+        assert_eq!(message.fields(), vec![("Project", "repo"), ("Directory", "/work/repo")]);
+
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Stop", payload: None, inline: false };
+        assert_eq!(ChatMessage::new(&data, &ctx, &config).color, 1);
+
+        let bad: ChatConfig = toml::from_str(r#"colors = { stop = "green" }"#).unwrap();
+        assert!(bad.validate().is_err());
+    }
+}
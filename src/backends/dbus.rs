@@ -0,0 +1,924 @@
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixStream;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use super::{DeliveryContext, NotificationBackend};
+use crate::private_dir;
+use crate::terminal_notifier::get_activate_session_binary;
+use crate::types::{DbusConfig, NotificationData};
+
+pub const NAME: &str = "dbus";
+
+const DESTINATION: &str = "org.freedesktop.Notifications";
+const OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+const INTERFACE: &str = "org.freedesktop.Notifications";
+
+const BUS_NAME: &str = "org.freedesktop.DBus";
+const BUS_PATH: &str = "/org/freedesktop/DBus";
+
+/// Action key used for the "go to terminal" button
+pub const ACTIVATE_ACTION: &str = "activate";
+
+/// How long a watcher waits for the user to click before giving up
+pub const WATCH_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How long connecting to the bus and each method call may take
+const CALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Linux desktop notifications through the freedesktop D-Bus interface
+pub struct DbusBackend {
+    config: DbusConfig,
+}
+
+impl DbusBackend {
+    pub fn new(config: DbusConfig) -> Self {
+        DbusBackend { config }
+    }
+}
+
+impl NotificationBackend for DbusBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        let click = &ctx.config.notifications.click_behavior;
+
+        // Only offer an action if something can act on it
+        let watcher = match (click.enabled, ctx.session_id) {
+            (true, Some(sid)) => get_activate_session_binary().map(|binary| (binary, sid)),
+            _ => None,
+        };
+
+        let actions: Vec<(&str, &str)> = if watcher.is_some() {
+            vec![("default", click.action_label.as_str()), (ACTIVATE_ACTION, click.action_label.as_str())]
+        } else {
+            Vec::new()
+        };
+
+        let mut bus = Bus::connect(self.config.address.as_deref())?;
+        // Subscribe before the notification exists, so even an instant click is seen
+        if watcher.is_some() {
+            bus.add_match(&signal_rule())?;
+        }
+        let notification_id = self.notify(&mut bus, data, ctx, &actions, ctx.config.notifications.timeout as i32)?;
+
+        // Hand the connection to a separate process that waits for the click,
+        // so the hook can return
+        if let Some((binary, sid)) = watcher {
+            if notification_id != 0 {
+                Command::new(binary)
+                    .arg("--dbus-notification").arg(notification_id.to_string()).arg(sid)
+                    .stdin(Stdio::from(OwnedFd::from(bus.into_stream())))
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn ask(
+        &self,
+        data: &NotificationData,
+        choices: &[&str],
+        timeout: Duration,
+        ctx: &DeliveryContext,
+    ) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+        let keys: Vec<String> = (0..choices.len()).map(|index| format!("choice-{}", index)).collect();
+        let actions: Vec<(&str, &str)> = keys.iter().map(String::as_str).zip(choices.iter().copied()).collect();
+
+        let mut bus = Bus::connect(self.config.address.as_deref())?;
+        bus.add_match(&signal_rule())?;
+
+        // Keep it on screen until answered; we close it ourselves on timeout
+        let notification_id = self.notify(&mut bus, data, ctx, &actions, 0)?;
+        let action = wait_for_action(&mut bus, notification_id, timeout)?;
+
+        if action.is_none() {
+            bus.call(DESTINATION, OBJECT_PATH, INTERFACE, "CloseNotification", vec![Value::U32(notification_id)]).ok();
+        }
+        Ok(action.and_then(|key| keys.iter().position(|k| *k == key)))
+    }
+}
+
+impl DbusBackend {
+    /// Show a notification and return its id
+    fn notify(
+        &self,
+        bus: &mut Bus,
+        data: &NotificationData,
+        ctx: &DeliveryContext,
+        actions: &[(&str, &str)],
+        expire_timeout: i32,
+    ) -> Result<u32, Box<dyn std::error::Error>> {
+        let body = notify_body(data, ctx, &self.config, actions, expire_timeout);
+        match bus.call(DESTINATION, OBJECT_PATH, INTERFACE, "Notify", body)?.first() {
+            Some(Value::U32(id)) => Ok(*id),
+            _ => Err("D-Bus Notify didn't return a notification id".into()),
+        }
+    }
+}
+
+/// The arguments of a `Notify` call
+fn notify_body(
+    data: &NotificationData,
+    ctx: &DeliveryContext,
+    config: &DbusConfig,
+    actions: &[(&str, &str)],
+    expire_timeout: i32,
+) -> Vec<Value> {
+    let actions = actions.iter()
+        .flat_map(|(key, label)| [Value::Str(key.to_string()), Value::Str(label.to_string())])
+        .collect();
+
+    let hint = |key: &str, value| Value::DictEntry(Box::new(Value::Str(key.to_string())), Box::new(Value::Variant(Box::new(value))));
+    let mut hints = vec![hint("urgency", Value::Byte(urgency_for_event(ctx.event)))];
+    if data.sound.is_empty() {
+        hints.push(hint("suppress-sound", Value::Bool(true)));
+    } else {
+        hints.push(hint("sound-name", Value::Str(sound_name(&data.sound).to_string())));
+    }
+
+    vec![
+        Value::Str(config.app_name.clone()),
+        Value::U32(0),                          // replaces_id
+        Value::Str(config.icon.clone()),
+        Value::Str(data.title.clone()),
+        Value::Str(data.body.clone()),
+        Value::Array("s".to_string(), actions),
+        Value::Array("{sv}".to_string(), hints),
+        Value::I32(expire_timeout),
+    ]
+}
+
+/// Signals from the notification server only, so no other client on the bus
+/// can click for the user
+fn signal_rule() -> String {
+    format!("type='signal',sender='{}',path='{}',interface='{}'", DESTINATION, OBJECT_PATH, INTERFACE)
+}
+
+/// Freedesktop urgency level: 0 = low, 1 = normal, 2 = critical
+pub fn urgency_for_event(event: &str) -> u8 {
+    match event {
+        "Notification" => 2,
+        "PreToolUse" => 0,
+        _ => 1,
+    }
+}
+
+/// Map the macOS sound names used in `SoundConfig` onto the freedesktop sound theme
+pub fn sound_name(sound: &str) -> &str {
+    match sound {
+        "Glass" | "Ping" => "message-new-instant",
+        "Pop" | "Tink" | "Morse" => "message",
+        "Hero" | "Purr" => "complete",
+        "Basso" | "Funk" => "dialog-warning",
+        "Sosumi" | "Submarine" | "Blow" | "Bottle" | "Frog" => "bell",
+        other => other,
+    }
+}
+
+/// Block until an action is invoked on `notification_id`.
+///
+/// Returns `None` if the notification is closed or the timeout elapses first.
+fn wait_for_action(bus: &mut Bus, notification_id: u32, timeout: Duration) -> io::Result<Option<String>> {
+    let deadline = Instant::now() + timeout;
+    while let Some(message) = bus.next_message(deadline)? {
+        if message.kind != SIGNAL || message.interface.as_deref() != Some(INTERFACE) {
+            continue;
+        }
+        match (message.member.as_deref(), message.body.as_slice()) {
+            (Some("ActionInvoked"), [Value::U32(id), Value::Str(action)]) if *id == notification_id => {
+                return Ok(Some(action.clone()));
+            }
+            (Some("NotificationClosed"), [Value::U32(id), ..]) if *id == notification_id => return Ok(None),
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+/// Block until an action is invoked on `notification_id`, over the bus
+/// connection `DbusBackend::send` hands over as stdin
+pub fn watch_inherited(notification_id: u32, timeout: Duration) -> Option<String> {
+    let stdin = io::stdin().as_fd().try_clone_to_owned().ok()?;
+    let mut bus = Bus::from_stream(UnixStream::from(stdin));
+    wait_for_action(&mut bus, notification_id, timeout).ok().flatten()
+}
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+const SIGNAL: u8 = 4;
+
+/// Messages larger than this are refused rather than buffered
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+
+/// Just enough of a D-Bus client to show notifications and hear about clicks
+struct Bus {
+    stream: UnixStream,
+    /// The part of the next message read so far
+    input: Vec<u8>,
+    /// Messages that arrived while waiting for a reply
+    queued: VecDeque<Message>,
+    next_serial: u32,
+}
+
+impl Bus {
+    /// Connect to `address`, or the session bus, and say hello
+    fn connect(address: Option<&str>) -> io::Result<Self> {
+        let address = match address {
+            Some(address) => address.to_string(),
+            None => session_bus_address()?,
+        };
+        let mut stream = connect_address(&address)?;
+        stream.set_write_timeout(Some(CALL_TIMEOUT))?;
+        stream.set_read_timeout(Some(CALL_TIMEOUT))?;
+        authenticate(&mut stream)?;
+
+        let mut bus = Bus::from_stream(stream);
+        bus.call(BUS_NAME, BUS_PATH, BUS_NAME, "Hello", Vec::new())?;
+        Ok(bus)
+    }
+
+    fn from_stream(stream: UnixStream) -> Self {
+        Bus { stream, input: Vec::new(), queued: VecDeque::new(), next_serial: 1 }
+    }
+
+    fn into_stream(self) -> UnixStream {
+        self.stream
+    }
+
+    fn add_match(&mut self, rule: &str) -> io::Result<()> {
+        self.call(BUS_NAME, BUS_PATH, BUS_NAME, "AddMatch", vec![Value::Str(rule.to_string())])?;
+        Ok(())
+    }
+
+    /// Send `message`, returning the serial it was given
+    fn send(&mut self, mut message: Message) -> io::Result<u32> {
+        message.serial = self.next_serial;
+        self.next_serial = self.next_serial.wrapping_add(1).max(1);
+        self.stream.write_all(&message.encode())?;
+        Ok(message.serial)
+    }
+
+    /// Call a method and wait for its reply. Whatever else arrives meanwhile
+    /// is kept for `next_message`.
+    fn call(&mut self, destination: &str, path: &str, interface: &str, member: &str, body: Vec<Value>) -> io::Result<Vec<Value>> {
+        let call = Message {
+            kind: METHOD_CALL,
+            path: Some(path.to_string()),
+            interface: Some(interface.to_string()),
+            member: Some(member.to_string()),
+            destination: Some(destination.to_string()),
+            body,
+            ..Message::default()
+        };
+        let serial = self.send(call)?;
+
+        let deadline = Instant::now() + CALL_TIMEOUT;
+        loop {
+            let message = self.read_message(deadline)?
+                .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, format!("no reply to D-Bus {}", member)))?;
+            if message.reply_serial != Some(serial) || !matches!(message.kind, METHOD_RETURN | ERROR) {
+                self.queued.push_back(message);
+                continue;
+            }
+            if message.kind == ERROR {
+                let detail = match message.body.first() {
+                    Some(Value::Str(detail)) => detail.as_str(),
+                    _ => "",
+                };
+                let name = message.error_name.as_deref().unwrap_or("unknown error");
+                return Err(io::Error::other(format!("D-Bus {} failed: {} {}", member, name, detail).trim_end().to_string()));
+            }
+            return Ok(message.body);
+        }
+    }
+
+    /// The next message, or `None` once `deadline` passes
+    fn next_message(&mut self, deadline: Instant) -> io::Result<Option<Message>> {
+        match self.queued.pop_front() {
+            Some(message) => Ok(Some(message)),
+            None => self.read_message(deadline),
+        }
+    }
+
+    /// Read one message. Nothing past its end is read, so the connection can
+    /// be handed to another process between messages.
+    fn read_message(&mut self, deadline: Instant) -> io::Result<Option<Message>> {
+        loop {
+            let wanted = match self.input.len() {
+                len if len < 16 => 16,
+                _ => message_len(&self.input)?,
+            };
+            if self.input.len() == wanted && wanted > 16 {
+                let message = Message::decode(&self.input)?;
+                self.input.clear();
+                return Ok(Some(message));
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+
+            let mut chunk = vec![0; (wanted - self.input.len()).min(64 * 1024)];
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the bus closed the connection")),
+                Ok(read) => self.input.extend_from_slice(&chunk[..read]),
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Log in as the user this process runs as
+fn authenticate(stream: &mut UnixStream) -> io::Result<()> {
+    let uid: String = private_dir::current_uid().to_string().bytes().map(|b| format!("{:02x}", b)).collect();
+    stream.write_all(format!("\0AUTH EXTERNAL {}\r\n", uid).as_bytes())?;
+
+    // Byte by byte, since the messages that follow mustn't be read here
+    let mut reply = Vec::new();
+    let mut byte = [0];
+    while !reply.ends_with(b"\r\n") && reply.len() < 512 {
+        stream.read_exact(&mut byte)?;
+        reply.push(byte[0]);
+    }
+    if !reply.starts_with(b"OK ") {
+        let reply = String::from_utf8_lossy(&reply);
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("the bus refused us: {}", reply.trim())));
+    }
+    stream.write_all(b"BEGIN\r\n")
+}
+
+/// Where the session bus is, from the environment
+fn session_bus_address() -> io::Result<String> {
+    if let Some(address) = std::env::var("DBUS_SESSION_BUS_ADDRESS").ok().filter(|a| !a.is_empty()) {
+        return Ok(address);
+    }
+    let socket = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(|runtime| std::path::PathBuf::from(runtime).join("bus"))
+        .filter(|socket| socket.exists())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no session bus: DBUS_SESSION_BUS_ADDRESS isn't set"))?;
+    Ok(format!("unix:path={}", escape_address_value(socket.as_os_str().as_bytes())))
+}
+
+/// Connect to the first reachable Unix socket in a D-Bus address list, e.g.
+/// `unix:path=/run/user/1000/bus`
+fn connect_address(addresses: &str) -> io::Result<UnixStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::InvalidInput, format!("no usable D-Bus address in {}", addresses));
+    for address in addresses.split(';') {
+        let Some(params) = address.strip_prefix("unix:") else { continue };
+        let param = |key: &str| params.split(',')
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| unescape_address_value(value));
+
+        let connected = match (param("path"), param("abstract")) {
+            (Some(path), _) => UnixStream::connect(OsStr::from_bytes(&path)),
+            (None, Some(name)) => connect_abstract(&name),
+            (None, None) => continue,
+        };
+        match connected {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+#[cfg(target_os = "linux")]
+fn connect_abstract(name: &[u8]) -> io::Result<UnixStream> {
+    use std::os::linux::net::SocketAddrExt;
+    UnixStream::connect_addr(&std::os::unix::net::SocketAddr::from_abstract_name(name)?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn connect_abstract(_name: &[u8]) -> io::Result<UnixStream> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "abstract sockets only exist on Linux"))
+}
+
+/// Decode the `%XX` escapes of a D-Bus address value
+fn unescape_address_value(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut at = 0;
+    while at < bytes.len() {
+        let escaped = (bytes[at] == b'%')
+            .then(|| value.get(at + 1..at + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                unescaped.push(byte);
+                at += 3;
+            }
+            None => {
+                unescaped.push(bytes[at]);
+                at += 1;
+            }
+        }
+    }
+    unescaped
+}
+
+fn escape_address_value(value: &[u8]) -> String {
+    value.iter().map(|&b| match b {
+        b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'/' | b'.' | b'\\' => (b as char).to_string(),
+        _ => format!("%{:02x}", b),
+    }).collect()
+}
+
+/// A D-Bus value, for the types notifications use
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Byte(u8),
+    Bool(bool),
+    I32(i32),
+    U32(u32),
+    Str(String),
+    ObjectPath(String),
+    Signature(String),
+    /// The element signature, which an empty array still needs
+    Array(String, Vec<Value>),
+    Struct(Vec<Value>),
+    DictEntry(Box<Value>, Box<Value>),
+    Variant(Box<Value>),
+}
+
+impl Value {
+    fn signature(&self) -> String {
+        match self {
+            Value::Byte(_) => "y".to_string(),
+            Value::Bool(_) => "b".to_string(),
+            Value::I32(_) => "i".to_string(),
+            Value::U32(_) => "u".to_string(),
+            Value::Str(_) => "s".to_string(),
+            Value::ObjectPath(_) => "o".to_string(),
+            Value::Signature(_) => "g".to_string(),
+            Value::Array(element, _) => format!("a{}", element),
+            Value::Struct(fields) => format!("({})", fields.iter().map(Value::signature).collect::<String>()),
+            Value::DictEntry(key, value) => format!("{{{}{}}}", key.signature(), value.signature()),
+            Value::Variant(_) => "v".to_string(),
+        }
+    }
+}
+
+/// The boundary a value of the type `signature` starts with starts on
+fn alignment(signature: &str) -> usize {
+    match signature.as_bytes().first() {
+        Some(b'b' | b'i' | b'u' | b's' | b'o' | b'a') => 4,
+        Some(b'(' | b'{') => 8,
+        _ => 1,
+    }
+}
+
+/// Split a signature into its complete types, e.g. `sa{sv}i` into `s`, `a{sv}` and `i`
+fn split_signature(signature: &str) -> io::Result<Vec<&str>> {
+    let bytes = signature.as_bytes();
+    let mut types = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let mut end = start;
+        while bytes.get(end) == Some(&b'a') {
+            end += 1;
+        }
+        let mut depth = 0;
+        loop {
+            match bytes.get(end) {
+                Some(b'(' | b'{') => depth += 1,
+                Some(b')' | b'}') => depth -= 1,
+                Some(_) => {}
+                None => return Err(invalid(format!("incomplete D-Bus signature {}", signature))),
+            }
+            end += 1;
+            if depth == 0 {
+                break;
+            }
+        }
+        types.push(&signature[start..end]);
+        start = end;
+    }
+    Ok(types)
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn align(&mut self, boundary: usize) {
+        while !self.buf.len().is_multiple_of(boundary) {
+            self.buf.push(0);
+        }
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn put(&mut self, value: &Value) {
+        match value {
+            Value::Byte(byte) => self.buf.push(*byte),
+            Value::Bool(flag) => self.u32(*flag as u32),
+            Value::I32(number) => self.u32(*number as u32),
+            Value::U32(number) => self.u32(*number),
+            Value::Str(text) | Value::ObjectPath(text) => {
+                self.u32(text.len() as u32);
+                self.buf.extend_from_slice(text.as_bytes());
+                self.buf.push(0);
+            }
+            Value::Signature(signature) => {
+                self.buf.push(signature.len() as u8);
+                self.buf.extend_from_slice(signature.as_bytes());
+                self.buf.push(0);
+            }
+            Value::Array(element, items) => {
+                self.u32(0);
+                let len_at = self.buf.len() - 4;
+                // The length doesn't count the padding before the first element
+                self.align(alignment(element));
+                let start = self.buf.len();
+                for item in items {
+                    self.put(item);
+                }
+                let len = (self.buf.len() - start) as u32;
+                self.buf[len_at..len_at + 4].copy_from_slice(&len.to_le_bytes());
+            }
+            Value::Struct(fields) => {
+                self.align(8);
+                for field in fields {
+                    self.put(field);
+                }
+            }
+            Value::DictEntry(key, value) => {
+                self.align(8);
+                self.put(key);
+                self.put(value);
+            }
+            Value::Variant(value) => {
+                self.put(&Value::Signature(value.signature()));
+                self.put(value);
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+    at: usize,
+    big_endian: bool,
+}
+
+impl Decoder<'_> {
+    fn take(&mut self, len: usize) -> io::Result<&[u8]> {
+        let bytes = self.buf.get(self.at..self.at + len).ok_or_else(|| invalid("truncated D-Bus message".to_string()))?;
+        self.at += len;
+        Ok(bytes)
+    }
+
+    fn align(&mut self, boundary: usize) -> io::Result<()> {
+        let padding = (boundary - self.at % boundary) % boundary;
+        self.take(padding).map(|_| ())
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.align(4)?;
+        let bytes: [u8; 4] = self.take(4)?.try_into().unwrap();
+        Ok(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn text(&mut self, len: usize) -> io::Result<String> {
+        let text = String::from_utf8(self.take(len)?.to_vec()).map_err(|_| invalid("D-Bus string isn't UTF-8".to_string()))?;
+        self.take(1)?;
+        Ok(text)
+    }
+
+    fn value(&mut self, signature: &str) -> io::Result<Value> {
+        let inner = || signature.get(1..signature.len() - 1).unwrap_or_default();
+        Ok(match signature.as_bytes().first() {
+            Some(b'y') => Value::Byte(self.take(1)?[0]),
+            Some(b'b') => Value::Bool(self.u32()? != 0),
+            Some(b'i') => Value::I32(self.u32()? as i32),
+            Some(b'u') => Value::U32(self.u32()?),
+            Some(b's') => {
+                let len = self.u32()? as usize;
+                Value::Str(self.text(len)?)
+            }
+            Some(b'o') => {
+                let len = self.u32()? as usize;
+                Value::ObjectPath(self.text(len)?)
+            }
+            Some(b'g') => {
+                let len = self.take(1)?[0] as usize;
+                Value::Signature(self.text(len)?)
+            }
+            Some(b'v') => {
+                let len = self.take(1)?[0] as usize;
+                let contained = self.text(len)?;
+                match split_signature(&contained)?.as_slice() {
+                    [single] => Value::Variant(Box::new(self.value(single)?)),
+                    _ => return Err(invalid(format!("bad D-Bus variant signature {}", contained))),
+                }
+            }
+            Some(b'a') => {
+                let len = self.u32()? as usize;
+                let element = &signature[1..];
+                self.align(alignment(element))?;
+                let end = self.at + len;
+                if end > self.buf.len() {
+                    return Err(invalid("truncated D-Bus array".to_string()));
+                }
+                let mut items = Vec::new();
+                while self.at < end {
+                    items.push(self.value(element)?);
+                }
+                Value::Array(element.to_string(), items)
+            }
+            Some(b'(') => {
+                self.align(8)?;
+                let fields = split_signature(inner())?.into_iter().map(|field| self.value(field)).collect::<io::Result<_>>()?;
+                Value::Struct(fields)
+            }
+            Some(b'{') => {
+                self.align(8)?;
+                match split_signature(inner())?.as_slice() {
+                    [key, value] => Value::DictEntry(Box::new(self.value(key)?), Box::new(self.value(value)?)),
+                    _ => return Err(invalid(format!("bad D-Bus dict entry {}", signature))),
+                }
+            }
+            _ => return Err(invalid(format!("unsupported D-Bus type {}", signature))),
+        })
+    }
+}
+
+/// A D-Bus message; only the header fields notifications need are kept
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Message {
+    kind: u8,
+    serial: u32,
+    path: Option<String>,
+    interface: Option<String>,
+    member: Option<String>,
+    error_name: Option<String>,
+    reply_serial: Option<u32>,
+    destination: Option<String>,
+    sender: Option<String>,
+    body: Vec<Value>,
+}
+
+impl Message {
+    fn encode(&self) -> Vec<u8> {
+        let field = |code: u8, value: Value| Value::Struct(vec![Value::Byte(code), Value::Variant(Box::new(value))]);
+        let mut fields = Vec::new();
+        fields.extend(self.path.clone().map(|path| field(1, Value::ObjectPath(path))));
+        fields.extend(self.interface.clone().map(|interface| field(2, Value::Str(interface))));
+        fields.extend(self.member.clone().map(|member| field(3, Value::Str(member))));
+        fields.extend(self.error_name.clone().map(|name| field(4, Value::Str(name))));
+        fields.extend(self.reply_serial.map(|serial| field(5, Value::U32(serial))));
+        fields.extend(self.destination.clone().map(|destination| field(6, Value::Str(destination))));
+        fields.extend(self.sender.clone().map(|sender| field(7, Value::Str(sender))));
+        if !self.body.is_empty() {
+            fields.push(field(8, Value::Signature(self.body.iter().map(Value::signature).collect())));
+        }
+
+        // The body starts on an 8 byte boundary, so it can be aligned on its own
+        let mut body = Encoder::default();
+        for value in &self.body {
+            body.put(value);
+        }
+
+        let mut message = Encoder { buf: vec![b'l', self.kind, 0, 1] };
+        message.u32(body.buf.len() as u32);
+        message.u32(self.serial);
+        message.put(&Value::Array("(yv)".to_string(), fields));
+        message.align(8);
+        message.buf.extend_from_slice(&body.buf);
+        message.buf
+    }
+
+    fn decode(buf: &[u8]) -> io::Result<Message> {
+        let mut decoder = Decoder { buf, at: 0, big_endian: buf[0] == b'B' };
+        let kind = buf[1];
+        decoder.at = 4;
+        let body_len = decoder.u32()? as usize;
+        let serial = decoder.u32()?;
+
+        let mut message = Message { kind, serial, ..Message::default() };
+        let mut signature = String::new();
+        let Value::Array(_, fields) = decoder.value("a(yv)")? else { unreachable!() };
+        for field in fields {
+            let Value::Struct(parts) = field else { continue };
+            let [Value::Byte(code), Value::Variant(value)] = parts.as_slice() else { continue };
+            match (code, value.as_ref()) {
+                (1, Value::ObjectPath(path)) => message.path = Some(path.clone()),
+                (2, Value::Str(interface)) => message.interface = Some(interface.clone()),
+                (3, Value::Str(member)) => message.member = Some(member.clone()),
+                (4, Value::Str(name)) => message.error_name = Some(name.clone()),
+                (5, Value::U32(serial)) => message.reply_serial = Some(*serial),
+                (6, Value::Str(destination)) => message.destination = Some(destination.clone()),
+                (7, Value::Str(sender)) => message.sender = Some(sender.clone()),
+                (8, Value::Signature(body)) => signature = body.clone(),
+                _ => {}
+            }
+        }
+
+        decoder.align(8)?;
+        let body_end = decoder.at + body_len;
+        if body_end != buf.len() {
+            return Err(invalid("D-Bus message length doesn't match its header".to_string()));
+        }
+        for value in split_signature(&signature)? {
+            message.body.push(decoder.value(value)?);
+        }
+        Ok(message)
+    }
+}
+
+/// The full length of the message whose first 16 bytes are `header`
+fn message_len(header: &[u8]) -> io::Result<usize> {
+    let number = |at: usize| {
+        let bytes: [u8; 4] = header[at..at + 4].try_into().unwrap();
+        match header[0] {
+            b'l' => Ok(u32::from_le_bytes(bytes) as usize),
+            b'B' => Ok(u32::from_be_bytes(bytes) as usize),
+            _ => Err(invalid("bad D-Bus message endianness".to_string())),
+        }
+    };
+    let fields_end = (16 + number(12)?).next_multiple_of(8);
+    let len = fields_end + number(4)?;
+    if len > MAX_MESSAGE_LEN {
+        return Err(invalid(format!("D-Bus message of {} bytes is too large", len)));
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Config;
+    use std::io::{BufRead, BufReader};
+    use std::thread;
+
+    #[test]
+    fn test_notify_body() {
+        let config = Config::default();
+        let ctx = DeliveryContext { config: &config, session_id: None, event: "Notification", payload: None, inline: false };
+        let data = NotificationData {
+            title: "Claude Needs Approval".to_string(),
+            body: "It's waiting".to_string(),
+            sound: "Glass".to_string(),
+        };
+
+        let body = notify_body(&data, &ctx, &DbusConfig::default(), &[("activate", "Go to Terminal")], 5000);
+        assert_eq!(body.iter().map(Value::signature).collect::<String>(), "susssasa{sv}i");
+        assert_eq!(body[0], Value::Str("Claude Notifier".to_string()));
+        assert_eq!(body[5], Value::Array("s".to_string(), vec![
+            Value::Str("activate".to_string()),
+            Value::Str("Go to Terminal".to_string()),
+        ]));
+        let Value::Array(_, hints) = &body[6] else { panic!("hints aren't an array") };
+        assert_eq!(hints[1], Value::DictEntry(
+            Box::new(Value::Str("sound-name".to_string())),
+            Box::new(Value::Variant(Box::new(Value::Str("message-new-instant".to_string())))),
+        ));
+        assert_eq!(body[7], Value::I32(5000));
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let config = Config::default();
+        let ctx = DeliveryContext { config: &config, session_id: None, event: "Stop", payload: None, inline: false };
+        let data = NotificationData { title: "Claude Finished".to_string(), body: String::new(), sound: String::new() };
+        let message = Message {
+            kind: METHOD_CALL,
+            serial: 3,
+            path: Some(OBJECT_PATH.to_string()),
+            interface: Some(INTERFACE.to_string()),
+            member: Some("Notify".to_string()),
+            destination: Some(DESTINATION.to_string()),
+            body: notify_body(&data, &ctx, &DbusConfig::default(), &[], -1),
+            ..Message::default()
+        };
+
+        let encoded = message.encode();
+        assert_eq!(encoded.len() % 8, 0);
+        assert_eq!(message_len(&encoded[..16]).unwrap(), encoded.len());
+        assert_eq!(Message::decode(&encoded).unwrap(), message);
+
+        assert_eq!(unescape_address_value("/tmp/a%2cb%3D"), b"/tmp/a,b=");
+        assert_eq!(escape_address_value(b"/run/user/1000/my bus"), "/run/user/1000/my%20bus");
+    }
+
+    /// Runs against a private bus with a stand-in notification server that
+    /// clicks as soon as it has answered, and is skipped without dbus-daemon
+    #[test]
+    fn test_ask_on_private_bus() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("skipping: dbus-daemon isn't installed");
+            return;
+        };
+
+        let mut server = Bus::connect(Some(&bus.address)).unwrap();
+        let owner = server.call(BUS_NAME, BUS_PATH, BUS_NAME, "RequestName", vec![Value::Str(DESTINATION.to_string()), Value::U32(0)]);
+        assert_eq!(owner.unwrap(), vec![Value::U32(1)]);
+        let server = thread::spawn(move || {
+            let mut calls = Vec::new();
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while let Some(call) = server.next_message(deadline).unwrap() {
+                if call.member.as_deref() != Some("Notify") {
+                    continue;
+                }
+                let id = calls.len() as u32 + 7;
+                let reply = Message {
+                    kind: METHOD_RETURN,
+                    reply_serial: Some(call.serial),
+                    destination: call.sender.clone(),
+                    body: vec![Value::U32(id)],
+                    ..Message::default()
+                };
+                server.send(reply).unwrap();
+                if let Value::Array(_, actions) = &call.body[5] {
+                    if let Some(Value::Str(key)) = actions.iter().rev().nth(1) {
+                        let click = Message {
+                            kind: SIGNAL,
+                            path: Some(OBJECT_PATH.to_string()),
+                            interface: Some(INTERFACE.to_string()),
+                            member: Some("ActionInvoked".to_string()),
+                            body: vec![Value::U32(id), Value::Str(key.clone())],
+                            ..Message::default()
+                        };
+                        server.send(click).unwrap();
+                    }
+                }
+                calls.push(call);
+                if calls.len() == 2 {
+                    break;
+                }
+            }
+            calls
+        });
+
+        let config = Config::default();
+        let backend = DbusBackend::new(DbusConfig { address: Some(bus.address.clone()), ..DbusConfig::default() });
+        let ctx = DeliveryContext { config: &config, session_id: None, event: "Stop", payload: None, inline: false };
+        let finished = NotificationData { title: "Claude Finished".to_string(), body: "Done".to_string(), sound: "Hero".to_string() };
+        backend.send(&finished, &ctx).unwrap();
+
+        let approval = NotificationData { title: "Claude Needs Approval".to_string(), body: "cargo test".to_string(), sound: "Glass".to_string() };
+        let ctx = DeliveryContext { event: "PreToolUse", ..ctx };
+        let answer = backend.ask(&approval, &["Allow", "Deny"], Duration::from_secs(5), &ctx).unwrap();
+        assert_eq!(answer, Some(1));
+
+        let calls = server.join().unwrap();
+        assert_eq!(calls[0].body[3], Value::Str("Claude Finished".to_string()));
+        assert_eq!(calls[1].body[7], Value::I32(0));
+    }
+
+    struct PrivateBus {
+        address: String,
+        daemon: std::process::Child,
+    }
+
+    impl PrivateBus {
+        /// A bus of our own, or `None` if dbus-daemon isn't installed
+        fn start() -> Option<Self> {
+            let daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn();
+            let mut daemon = match daemon {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+                started => started.expect("dbus-daemon starts"),
+            };
+
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+            Some(PrivateBus { address: address.trim().to_string(), daemon })
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            self.daemon.kill().ok();
+            self.daemon.wait().ok();
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use super::chat::{self, ChatConfig, ChatMessage};
+use super::{DeliveryContext, NotificationBackend};
+use crate::template::{format_timestamp, truncate};
+use crate::types::NotificationData;
+
+pub const NAME: &str = "discord";
+
+/// Discord webhooks, formatted as an embed
+pub struct DiscordBackend {
+    config: ChatConfig,
+}
+
+impl DiscordBackend {
+    pub fn new(config: ChatConfig) -> Self {
+        DiscordBackend { config }
+    }
+}
+
+impl NotificationBackend for DiscordBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        chat::post(NAME, &self.config, data, ctx, format)
+    }
+}
+
+pub fn format(message: &ChatMessage) -> Value {
+    let fields: Vec<Value> = message.fields().into_iter()
+        .map(|(label, value)| json!({ "name": label, "value": truncate(value, 1024), "inline": true }))
+        .collect();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+
+    json!({
+        "embeds": [{
+            "title": truncate(&message.title, 256),
+            "description": truncate(&message.body, 4096),
+            "color": message.color,
+            "fields": fields,
+            "footer": { "text": message.event },
+            "timestamp": format_timestamp(now),
+        }],
+        // Keep @everyone and friends in a message body from pinging anyone
+        "allowed_mentions": { "parse": [] },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{fixtures, stub};
+    use crate::types::Config;
+
+    #[test]
+    fn test_embed() {
+        let (url, received) = stub::serve(vec![(204, String::new())]);
+        let config: ChatConfig = fixtures::config(&url, r#"
+            spool = false
+            [[channels]]
+            url = "{url}/api/webhooks/1/token"
+            events = ["*"]
+        "#);
+
+        let app = Config::default();
+        let payload = fixtures::payload(json!({
+            "hook_event_name": "Notification",
+            "session_id": "abc123",
+            "cwd": "/work/repo",
+            "message": "Claude needs your permission to use Bash"
+        }));
+        let data = fixtures::approval("Bash");
+        let ctx = DeliveryContext { config: &app, session_id: Some("abc123"), event: "Notification", payload: Some(&payload), inline: false };
+        DiscordBackend::new(config).send(&data, &ctx).unwrap();
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.path, "/api/webhooks/1/token");
+        let body: Value = serde_json::from_str(&request.body).unwrap();
+        let embed = &body["embeds"][0];
+        assert_eq!(embed["title"], "Claude Needs Approval");
+        assert_eq!(embed["color"], 0xecb22e);
+        assert_eq!(embed["footer"]["text"], "Notification");
+        assert_eq!(embed["fields"][0], json!({ "name": "Project", "value": "repo", "inline": true }));
+        assert_eq!(embed["fields"][2]["value"], "abc123");
+    }
+
+    #[test]
+    fn test_limits_count_the_ellipsis() {
+        let app = Config::default();
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Stop", payload: None, inline: false };
+        let data = NotificationData { title: "t".repeat(257), body: "b".repeat(4097), ..fixtures::finished("") };
+        let body = format(&ChatMessage::new(&data, &ctx, &ChatConfig::default()));
+
+        let title = body["embeds"][0]["title"].as_str().unwrap();
+        assert_eq!(title.chars().count(), 256);
+        assert!(title.ends_with('…'));
+        assert_eq!(body["embeds"][0]["description"].as_str().unwrap().chars().count(), 4096);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::webhook::{self, RetryConfig};
+use super::{DeliveryContext, NotificationBackend};
+use crate::http::Request;
+use crate::types::{event_config_key, NotificationData};
+
+pub const NAME: &str = "gotify";
+
+/// Highest priority the Gotify Android app shows without sound or vibration
+const SILENT_PRIORITY: u8 = 3;
+
+/// Push notifications through a Gotify server, configured under `[backends.gotify]`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GotifyConfig {
+    #[serde(default)]
+    pub server: String,
+    /// The application token
+    #[serde(default)]
+    pub token: String,
+    /// Priority from 0 to 10 by event key, over the defaults
+    #[serde(default)]
+    pub priorities: BTreeMap<String, u8>,
+    /// URL opened when the notification is tapped
+    #[serde(default)]
+    pub click_url: Option<String>,
+    /// Timeouts and retries. `spool` and `spool_dir` have no effect, since
+    /// every request holds the application token.
+    #[serde(flatten)]
+    pub retry: RetryConfig,
+}
+
+impl GotifyConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some((event, priority)) = self.priorities.iter().find(|(_, p)| **p > 10) {
+            return Err(format!("priorities.{}: {} is not between 0 and 10", event, priority));
+        }
+        Ok(())
+    }
+
+    fn priority(&self, event_key: &str) -> u8 {
+        self.priorities.get(event_key).copied().unwrap_or_else(|| default_priority(event_key))
+    }
+}
+
+pub struct GotifyBackend {
+    config: GotifyConfig,
+}
+
+impl GotifyBackend {
+    pub fn new(config: GotifyConfig) -> Self {
+        GotifyBackend { config }
+    }
+
+    fn message(&self, data: &NotificationData, ctx: &DeliveryContext) -> Value {
+        // Gotify has no sounds of its own; priority decides whether the phone
+        // makes one, so a silenced notification stays below that threshold
+        let mut priority = self.config.priority(&event_config_key(ctx.event));
+        if data.sound.is_empty() {
+            priority = priority.min(SILENT_PRIORITY);
+        }
+
+        let mut message = json!({
+            "title": data.title,
+            "message": data.body,
+            "priority": priority,
+        });
+        if let Some(ref url) = self.config.click_url {
+            message["extras"] = json!({ "client::notification": { "click": { "url": url } } });
+        }
+        message
+    }
+}
+
+impl NotificationBackend for GotifyBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        if self.config.server.is_empty() || self.config.token.is_empty() {
+            return Err("backends.gotify.server and token must be set".into());
+        }
+
+        let url = format!("{}/message", self.config.server.trim_end_matches('/'));
+        let request = Request::new("POST", &url)
+            .header("X-Gotify-Key", &self.config.token)
+            .timeout(self.config.retry.timeout())
+            .json(&self.message(data, ctx))
+            .secret();
+        webhook::deliver(&request, &self.config.retry, NAME, ctx)?;
+        Ok(())
+    }
+}
+
+fn default_priority(event_key: &str) -> u8 {
+    match event_key {
+        "notification" => 8,
+        "pre_tool_use" | "post_tool_use" | "user_prompt_submit" => 2,
+        _ => 5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{fixtures, stub};
+    use crate::types::Config;
+
+    #[test]
+    fn test_priority_by_event_and_sound() {
+        let (url, received) = stub::serve(vec![(200, "{}".to_string()); 2]);
+        let config: GotifyConfig = fixtures::config(&url, r#"
+            server = "{url}/"
+            token = "app-token"
+            click_url = "vscode://"
+            spool = false
+        "#);
+        assert!(config.validate().is_ok());
+        let backend = GotifyBackend::new(config);
+        let app = Config::default();
+
+        let approval = fixtures::approval("Bash");
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Notification", payload: None, inline: false };
+        backend.send(&approval, &ctx).unwrap();
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.path, "/message");
+        assert_eq!(request.header("X-Gotify-Key"), Some("app-token"));
+        let message: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(message["priority"], 8);
+        assert_eq!(message["extras"]["client::notification"]["click"]["url"], "vscode://");
+
+        // Quiet hours clear the sound, which keeps the phone quiet too
+        let silent = NotificationData { sound: String::new(), ..approval };
+        backend.send(&silent, &ctx).unwrap();
+        let message: Value = serde_json::from_str(&received.recv().unwrap().body).unwrap();
+        assert_eq!(message["priority"], 3);
+    }
+}
@@ -0,0 +1,168 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::chat::{escape_html, ChatConfig, ChatMessage};
+use super::webhook::{self, RetryConfig};
+use super::{DeliveryContext, NotificationBackend};
+use crate::http::Request;
+use crate::rules::glob_match;
+use crate::types::NotificationData;
+
+pub const NAME: &str = "matrix";
+
+/// Notices in a Matrix room, configured under `[backends.matrix]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MatrixConfig {
+    /// Base URL of the homeserver's client-server API, e.g. `https://matrix.org`
+    #[serde(default)]
+    pub homeserver: String,
+    #[serde(default)]
+    pub access_token: String,
+    /// Room id such as `!abc123:example.org`; the account must have joined it
+    #[serde(default)]
+    pub room_id: String,
+    /// Events posted to the room, as globs
+    #[serde(default = "default_events")]
+    pub events: Vec<String>,
+    /// Timeouts and retries. `spool` and `spool_dir` have no effect, since
+    /// every request holds the access token.
+    #[serde(flatten)]
+    pub retry: RetryConfig,
+}
+
+impl Default for MatrixConfig {
+    fn default() -> Self {
+        Self {
+            homeserver: String::new(),
+            access_token: String::new(),
+            room_id: String::new(),
+            events: default_events(),
+            retry: RetryConfig::default(),
+        }
+    }
+}
+
+pub struct MatrixBackend {
+    config: MatrixConfig,
+}
+
+impl MatrixBackend {
+    pub fn new(config: MatrixConfig) -> Self {
+        MatrixBackend { config }
+    }
+}
+
+impl NotificationBackend for MatrixBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        if self.config.homeserver.is_empty() || self.config.access_token.is_empty() || self.config.room_id.is_empty() {
+            return Err("backends.matrix.homeserver, access_token and room_id must be set".into());
+        }
+        if !self.config.events.iter().any(|pattern| glob_match(pattern, ctx.event)) {
+            return Ok(());
+        }
+
+        // The homeserver drops a repeated transaction id, so retries of this
+        // request can't post the notice twice
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+        let txn_id = format!("claude-notifier-{}-{}", nanos, std::process::id());
+        let url = format!(
+            "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+            self.config.homeserver.trim_end_matches('/'),
+            encode_path_segment(&self.config.room_id),
+            txn_id
+        );
+
+        let content = notice(&ChatMessage::new(data, ctx, &ChatConfig::default()));
+        let request = Request::new("PUT", &url)
+            .header("Authorization", &format!("Bearer {}", self.config.access_token))
+            .timeout(self.config.retry.timeout())
+            .json(&content)
+            .secret();
+
+        webhook::deliver(&request, &self.config.retry, NAME, ctx)?;
+        Ok(())
+    }
+}
+
+/// An `m.notice`, which clients show less prominently and bots never answer
+pub fn notice(message: &ChatMessage) -> Value {
+    let mut body = format!("{}\n{}", message.title, message.body);
+    let mut html = format!("<strong>{}</strong><br>{}", escape_html(&message.title), escape_html(&message.body).replace('\n', "<br>"));
+
+    for (label, value) in message.fields() {
+        body.push_str(&format!("\n{}: {}", label, value));
+        html.push_str(&format!("<br><em>{}:</em> <code>{}</code>", label, escape_html(value)));
+    }
+
+    json!({
+        "msgtype": "m.notice",
+        "body": body,
+        "format": "org.matrix.custom.html",
+        "formatted_body": html,
+    })
+}
+
+/// Percent-encode everything but unreserved characters, as room ids contain
+/// `!` and `:`
+fn encode_path_segment(segment: &str) -> String {
+    segment.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn default_events() -> Vec<String> { vec!["*".to_string()] }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{fixtures, stub};
+    use crate::types::Config;
+
+    #[test]
+    fn test_notice_retried_with_the_same_transaction() {
+        let (url, received) = stub::serve(vec![
+            (502, String::new()),
+            (200, r#"{"event_id":"$abc"}"#.to_string()),
+        ]);
+        let config: MatrixConfig = fixtures::config(&url, r#"
+            homeserver = "{url}/"
+            access_token = "syt_secret"
+            room_id = "!room:example.org"
+            backoff_ms = 1
+            spool = false
+        "#);
+
+        let app = Config::default();
+        let payload = fixtures::payload(json!({
+            "hook_event_name": "Stop",
+            "cwd": "/work/repo"
+        }));
+        let data = fixtures::finished("Fixed <b>it</b>\nand tested");
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Stop", payload: Some(&payload), inline: false };
+        MatrixBackend::new(config).send(&data, &ctx).unwrap();
+
+        let requests: Vec<_> = received.iter().take(2).collect();
+        assert_eq!(requests[0].method, "PUT");
+        assert!(requests[0].path.starts_with("/_matrix/client/v3/rooms/%21room%3Aexample.org/send/m.room.message/claude-notifier-"));
+        assert_eq!(requests[0].path, requests[1].path);
+        assert_eq!(requests[1].header("Authorization"), Some("Bearer syt_secret"));
+
+        let content: Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert_eq!(content["msgtype"], "m.notice");
+        assert_eq!(content["body"], "Claude Finished\nFixed <b>it</b>\nand tested\nProject: repo\nDirectory: /work/repo");
+        assert_eq!(
+            content["formatted_body"],
+            "<strong>Claude Finished</strong><br>Fixed &lt;b&gt;it&lt;/b&gt;<br>and tested\
+             <br><em>Project:</em> <code>repo</code><br><em>Directory:</em> <code>/work/repo</code>"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+pub mod terminal_notifier;
+pub mod dbus;
+pub mod ntfy;
+pub mod webhook;
+pub mod chat;
+pub mod slack;
+pub mod discord;
+pub mod smtp;
+pub mod telegram;
+pub mod matrix;
+pub mod pushover;
+pub mod gotify;
+pub mod mqtt;
+pub mod osc;
+
+use std::time::Duration;
+
+use crate::types::{Config, HookPayload, NotificationData};
+
+/// Everything a backend may need besides the notification itself
+pub struct DeliveryContext<'a> {
+    pub config: &'a Config,
+    pub session_id: Option<&'a str>,
+    pub event: &'a str,
+    /// The hook payload behind the notification; summaries and escalations have none
+    pub payload: Option<&'a HookPayload>,
+    /// Delivered by the hook itself, which Claude waits on, rather than by a
+    /// detached process or the daemon
+    pub inline: bool,
+}
+
+/// A destination that a `NotificationData` can be delivered to
+pub trait NotificationBackend {
+    /// Name used to select the backend in `notifications.backends`
+    fn name(&self) -> &str;
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Show `data` with a button per entry in `choices` and block until one is
+    /// picked. Returns the index of the choice, or `None` if the notification
+    /// was dismissed or `timeout` elapsed.
+    fn ask(
+        &self,
+        _data: &NotificationData,
+        _choices: &[&str],
+        _timeout: Duration,
+        _ctx: &DeliveryContext,
+    ) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+        Err(format!("{} can't ask for a decision", self.name()).into())
+    }
+}
+
+/// Outcome of delivering a notification through a single backend
+#[derive(Debug)]
+pub struct DeliveryReport {
+    pub backend: String,
+    pub result: Result<(), String>,
+}
+
+impl DeliveryReport {
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Build the backend registered under `name`, if any
+pub fn create_backend(name: &str, config: &Config) -> Option<Box<dyn NotificationBackend>> {
+    match name {
+        terminal_notifier::NAME => Some(Box::new(terminal_notifier::TerminalNotifierBackend)),
+        dbus::NAME => Some(Box::new(dbus::DbusBackend::new(config.backends.dbus.clone()))),
+        ntfy::NAME => Some(Box::new(ntfy::NtfyBackend::new(config.backends.ntfy.clone()))),
+        webhook::NAME => Some(Box::new(webhook::WebhookBackend::new(config.backends.webhook.clone()))),
+        slack::NAME => Some(Box::new(slack::SlackBackend::new(config.backends.slack.clone()))),
+        discord::NAME => Some(Box::new(discord::DiscordBackend::new(config.backends.discord.clone()))),
+        smtp::NAME => Some(Box::new(smtp::SmtpBackend::new(config.backends.smtp.clone()))),
+        telegram::NAME => Some(Box::new(telegram::TelegramBackend::new(config.backends.telegram.clone()))),
+        matrix::NAME => Some(Box::new(matrix::MatrixBackend::new(config.backends.matrix.clone()))),
+        pushover::NAME => Some(Box::new(pushover::PushoverBackend::new(config.backends.pushover.clone()))),
+        gotify::NAME => Some(Box::new(gotify::GotifyBackend::new(config.backends.gotify.clone()))),
+        mqtt::NAME => Some(Box::new(mqtt::MqttBackend::new(config.backends.mqtt.clone()))),
+        osc::NAME => Some(Box::new(osc::OscBackend::new(config.backends.osc.clone()))),
+        _ => None,
+    }
+}
+
+/// Deliver a notification through every backend selected in the config
+pub fn dispatch(data: &NotificationData, ctx: &DeliveryContext) -> Vec<DeliveryReport> {
+    dispatch_named(&ctx.config.notifications.backends, data, ctx)
+}
+
+/// Deliver a notification through the named backends
+pub fn dispatch_named(names: &[String], data: &NotificationData, ctx: &DeliveryContext) -> Vec<DeliveryReport> {
+    let mut reports = Vec::new();
+
+    for name in names {
+        match create_backend(name, ctx.config) {
+            Some(backend) => reports.push(deliver(backend.as_ref(), data, ctx)),
+            None => reports.push(DeliveryReport {
+                backend: name.clone(),
+                result: Err(format!("Unknown notification backend: {}", name)),
+            }),
+        }
+    }
+
+    reports
+}
+
+/// Ask through the first of the named backends that is able to
+pub fn ask_named(
+    names: &[String],
+    data: &NotificationData,
+    choices: &[&str],
+    timeout: Duration,
+    ctx: &DeliveryContext,
+) -> Result<Option<usize>, String> {
+    let mut errors = Vec::new();
+
+    for name in names {
+        let Some(backend) = create_backend(name, ctx.config) else {
+            errors.push(format!("Unknown notification backend: {}", name));
+            continue;
+        };
+        match backend.ask(data, choices, timeout, ctx) {
+            Ok(choice) => return Ok(choice),
+            Err(e) => errors.push(format!("{}: {}", name, e)),
+        }
+    }
+
+    Err(errors.join("; "))
+}
+
+/// Deliver a notification through an already constructed set of backends
+pub fn dispatch_to(
+    backends: &[Box<dyn NotificationBackend>],
+    data: &NotificationData,
+    ctx: &DeliveryContext,
+) -> Vec<DeliveryReport> {
+    backends.iter()
+        .map(|backend| deliver(backend.as_ref(), data, ctx))
+        .collect()
+}
+
+fn deliver(backend: &dyn NotificationBackend, data: &NotificationData, ctx: &DeliveryContext) -> DeliveryReport {
+    DeliveryReport {
+        backend: backend.name().to_string(),
+        result: backend.send(data, ctx).map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeBackend {
+        name: &'static str,
+        fail: bool,
+    }
+
+    impl NotificationBackend for FakeBackend {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn send(&self, _data: &NotificationData, _ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+            if self.fail {
+                Err("boom".into())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn sample_data() -> NotificationData {
+        NotificationData {
+            title: "Title".to_string(),
+            body: "Body".to_string(),
+            sound: "Pop".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_fan_out_reports_each_backend() {
+        let config = Config::default();
+        let ctx = DeliveryContext { config: &config, session_id: None, event: "Stop", payload: None, inline: false };
+        let backends: Vec<Box<dyn NotificationBackend>> = vec![
+            Box::new(FakeBackend { name: "ok", fail: false }),
+            Box::new(FakeBackend { name: "broken", fail: true }),
+        ];
+
+        let reports = dispatch_to(&backends, &sample_data(), &ctx);
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].backend, "ok");
+        assert!(reports[0].is_success());
+        assert_eq!(reports[1].backend, "broken");
+        assert_eq!(reports[1].result, Err("boom".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_backend_is_reported() {
+        let mut config = Config::default();
+        config.notifications.backends = vec!["carrier-pigeon".to_string()];
+        let ctx = DeliveryContext { config: &config, session_id: None, event: "Stop", payload: None, inline: false };
+
+        let reports = dispatch(&sample_data(), &ctx);
+        assert_eq!(reports.len(), 1);
+        assert!(!reports[0].is_success());
+    }
+}
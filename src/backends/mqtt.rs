@@ -0,0 +1,513 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::webhook::payload_or_stub;
+use super::{DeliveryContext, NotificationBackend};
+use crate::debounce::now_ms;
+use crate::session_store::SessionStore;
+use crate::template::{format_timestamp, Template, TemplateContext};
+use crate::types::{HookEvent, HookPayload, NotificationData};
+
+pub const NAME: &str = "mqtt";
+
+/// Publishing to an MQTT broker, configured under `[backends.mqtt]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MqttConfig {
+    #[serde(default)]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Prefix of the id each connection uses, followed by the process id and
+    /// a counter so concurrent connections don't cut each other off.
+    /// Defaults to `claude-notifier`.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// Template for the topic each notification is published to
+    #[serde(default = "default_topic")]
+    pub topic: String,
+    /// Template for the retained topic holding each session's state; empty
+    /// disables state tracking
+    #[serde(default = "default_state_topic")]
+    pub state_topic: String,
+    /// 0 publishes and forgets, 1 waits for the broker to acknowledge
+    #[serde(default = "default_qos")]
+    pub qos: u8,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: default_port(),
+            username: None,
+            password: None,
+            client_id: None,
+            topic: default_topic(),
+            state_topic: default_state_topic(),
+            qos: default_qos(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+impl MqttConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.qos > 1 {
+            return Err(format!("qos: {} is not supported, use 0 or 1", self.qos));
+        }
+        // MQTT 3.1.1 only sends a password along with a username
+        if self.password.is_some() && self.username.is_none() {
+            return Err("password: needs a username".to_string());
+        }
+        for (key, source) in [("topic", &self.topic), ("state_topic", &self.state_topic)] {
+            Template::parse(source)
+                .and_then(|template| template.validate_fields())
+                .map_err(|e| format!("{}: {}", key, e))?;
+        }
+        Ok(())
+    }
+
+    /// Whether every hook event should update the session's state topic
+    pub fn tracks_state(&self) -> bool {
+        !self.host.is_empty() && !self.state_topic.is_empty()
+    }
+
+    fn connect(&self) -> io::Result<Client> {
+        static CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+        let client_id = format!(
+            "{}-{}-{}",
+            self.client_id.as_deref().unwrap_or("claude-notifier"),
+            std::process::id(),
+            CONNECTIONS.fetch_add(1, Ordering::Relaxed),
+        );
+        Client::connect(
+            (self.host.as_str(), self.port),
+            &client_id,
+            self.username.as_deref(),
+            self.password.as_deref(),
+            Duration::from_secs(self.timeout_secs),
+        )
+    }
+}
+
+pub struct MqttBackend {
+    config: MqttConfig,
+}
+
+impl MqttBackend {
+    pub fn new(config: MqttConfig) -> Self {
+        MqttBackend { config }
+    }
+}
+
+impl NotificationBackend for MqttBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        if self.config.host.is_empty() {
+            return Err("backends.mqtt.host is not set".into());
+        }
+
+        let payload = payload_or_stub(ctx);
+        let template_ctx = TemplateContext { payload: &payload, terminal: None, title: &data.title, body: &data.body };
+        let topic = render_topic(&self.config.topic, &template_ctx)?;
+        let message = json!({
+            "event": ctx.event,
+            "session_id": ctx.session_id,
+            "title": data.title,
+            "body": data.body,
+            "sound": data.sound,
+            "timestamp": format_timestamp(now_ms() / 1000),
+            "payload": *payload,
+        });
+
+        let mut client = self.config.connect()?;
+        client.publish(&topic, message.to_string().as_bytes(), false, self.config.qos)?;
+        client.disconnect()?;
+        Ok(())
+    }
+}
+
+/// What a session is doing, as far as its hook events tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    Idle,
+    Working,
+    RunningTool,
+    AwaitingApproval,
+    Finished,
+}
+
+impl SessionState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionState::Idle => "idle",
+            SessionState::Working => "working",
+            SessionState::RunningTool => "running_tool",
+            SessionState::AwaitingApproval => "awaiting_approval",
+            SessionState::Finished => "finished",
+        }
+    }
+}
+
+/// The state a session is in after `event`. `None` for events that end the
+/// session or say nothing about it.
+pub fn state_after(event: &HookEvent) -> Option<SessionState> {
+    match event {
+        HookEvent::SessionStart(_) => Some(SessionState::Idle),
+        // Also sent when Claude has been waiting for a prompt for a while
+        HookEvent::Notification(_) if event.is_idle_notice() => Some(SessionState::Idle),
+        HookEvent::Notification(_) => Some(SessionState::AwaitingApproval),
+        HookEvent::PreToolUse(_) => Some(SessionState::RunningTool),
+        HookEvent::UserPromptSubmit(_) | HookEvent::PostToolUse(_) | HookEvent::SubagentStop(_) | HookEvent::PreCompact(_) => {
+            Some(SessionState::Working)
+        }
+        HookEvent::Stop(_) => Some(SessionState::Finished),
+        HookEvent::SessionEnd(_) | HookEvent::Unknown(_) => None,
+    }
+}
+
+/// Update the session's retained state topic for a hook event that ran at
+/// `at_ms`. The state is cleared when the session ends, so brokers don't keep
+/// it forever.
+///
+/// Hooks are delivered concurrently, so a slow one can finish after the
+/// session has moved on. An event older than the newest state already claimed
+/// for the session is dropped. The claim is made with the session locked, but
+/// the publish happens after, so a slow broker doesn't hold up the session's
+/// other hooks.
+pub fn publish_state(config: &MqttConfig, store: &SessionStore, payload: &HookPayload, at_ms: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let claimed = payload.session_id.as_deref().and_then(|session_id| {
+        store.update_session(session_id, |session| {
+            let newest = at_ms >= session.mqtt_state_at_ms;
+            if newest {
+                session.mqtt_state_at_ms = at_ms;
+            }
+            newest
+        })
+    });
+    // A session that isn't stored has nothing to be out of order with
+    if claimed == Some(false) {
+        return Ok(());
+    }
+    publish_state_message(config, payload, at_ms)
+}
+
+fn publish_state_message(config: &MqttConfig, payload: &HookPayload, at_ms: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let message = match (&payload.event, state_after(&payload.event)) {
+        (HookEvent::SessionEnd(_), _) => Vec::new(),
+        (_, Some(state)) => json!({
+            "state": state.as_str(),
+            "session_id": payload.session_id,
+            "event": payload.event.name(),
+            "tool": payload.event.tool_name(),
+            "cwd": payload.cwd,
+            "timestamp": format_timestamp(at_ms / 1000),
+            "timestamp_ms": at_ms,
+        }).to_string().into_bytes(),
+        (_, None) => return Ok(()),
+    };
+
+    let template_ctx = TemplateContext { payload, terminal: None, title: "", body: "" };
+    let topic = render_topic(&config.state_topic, &template_ctx)?;
+
+    let mut client = config.connect()?;
+    client.publish(&topic, &message, true, config.qos)?;
+    client.disconnect()?;
+    Ok(())
+}
+
+/// Render a topic, keeping the values it's built from from adding levels or
+/// wildcards
+fn render_topic(source: &str, ctx: &TemplateContext) -> Result<String, String> {
+    let template = Template::parse(source)?;
+    Ok(template.render(|path| ctx.resolve(path).map(|value| value.replace(['/', '+', '#'], "_"))))
+}
+
+/// Just enough of an MQTT 3.1.1 client to publish
+struct Client {
+    stream: TcpStream,
+    next_packet_id: u16,
+}
+
+impl Client {
+    fn connect(
+        address: impl ToSocketAddrs,
+        client_id: &str,
+        username: Option<&str>,
+        password: Option<&str>,
+        timeout: Duration,
+    ) -> io::Result<Self> {
+        let address = address.to_socket_addrs()?.next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "broker address didn't resolve"))?;
+        let stream = TcpStream::connect_timeout(&address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        // Clean session, plus the flags for the credentials that follow
+        let mut flags = 0x02;
+        let mut body = Vec::new();
+        put_string(&mut body, "MQTT");
+        body.push(4);
+        let flags_at = body.len();
+        body.push(0);
+        body.extend_from_slice(&60u16.to_be_bytes());
+        put_string(&mut body, client_id);
+        if let Some(username) = username {
+            flags |= 0x80;
+            put_string(&mut body, username);
+        }
+        if let Some(password) = password {
+            flags |= 0x40;
+            put_string(&mut body, password);
+        }
+        body[flags_at] = flags;
+
+        let mut client = Client { stream, next_packet_id: 1 };
+        client.send_packet(0x10, &body)?;
+
+        let (kind, reply) = client.read_packet()?;
+        match (kind >> 4, reply.get(1)) {
+            (2, Some(0)) => Ok(client),
+            (2, Some(code)) => Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("broker refused the connection ({})", code))),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "expected CONNACK from broker")),
+        }
+    }
+
+    fn publish(&mut self, topic: &str, message: &[u8], retain: bool, qos: u8) -> io::Result<()> {
+        let mut body = Vec::new();
+        put_string(&mut body, topic);
+        let packet_id = self.next_packet_id;
+        if qos > 0 {
+            body.extend_from_slice(&packet_id.to_be_bytes());
+            self.next_packet_id = self.next_packet_id.wrapping_add(1).max(1);
+        }
+        body.extend_from_slice(message);
+        self.send_packet(0x30 | (qos.min(1) << 1) | retain as u8, &body)?;
+
+        if qos > 0 {
+            let (kind, reply) = self.read_packet()?;
+            if kind >> 4 != 4 || reply[..] != packet_id.to_be_bytes() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "expected PUBACK from broker"));
+            }
+        }
+        Ok(())
+    }
+
+    fn disconnect(mut self) -> io::Result<()> {
+        self.send_packet(0xe0, &[])
+    }
+
+    fn send_packet(&mut self, kind: u8, body: &[u8]) -> io::Result<()> {
+        let mut packet = vec![kind];
+        // Remaining length: 7 bits per byte, high bit set while more follow
+        let mut length = body.len();
+        loop {
+            let mut byte = (length % 128) as u8;
+            length /= 128;
+            if length > 0 {
+                byte |= 0x80;
+            }
+            packet.push(byte);
+            if length == 0 {
+                break;
+            }
+        }
+        packet.extend_from_slice(body);
+        self.stream.write_all(&packet)
+    }
+
+    fn read_packet(&mut self) -> io::Result<(u8, Vec<u8>)> {
+        read_packet(&mut self.stream)
+    }
+}
+
+fn read_packet(stream: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut byte = [0u8; 1];
+    stream.read_exact(&mut byte)?;
+    let kind = byte[0];
+
+    let mut length = 0usize;
+    for shift in 0..4 {
+        stream.read_exact(&mut byte)?;
+        length |= ((byte[0] & 0x7f) as usize) << (7 * shift);
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body)?;
+    Ok((kind, body))
+}
+
+fn put_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+fn default_port() -> u16 { 1883 }
+fn default_topic() -> String { "claude/{session_id | default:unknown}/{event}".to_string() }
+fn default_state_topic() -> String { "claude/{session_id | default:unknown}/state".to_string() }
+fn default_qos() -> u8 { 1 }
+fn default_timeout_secs() -> u64 { 5 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::fixtures;
+    use crate::types::Config;
+    use serde_json::Value;
+    use std::net::TcpListener;
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+
+    /// A message as the broker received it
+    #[derive(Debug)]
+    struct Published {
+        client_id: String,
+        topic: String,
+        message: Vec<u8>,
+        retain: bool,
+    }
+
+    /// Accept `connections` clients, acknowledging everything, and hand back
+    /// the credentials of the first and every message published
+    fn broker(connections: usize) -> (u16, Receiver<(String, Published)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for _ in 0..connections {
+                let (mut stream, _) = listener.accept().unwrap();
+                let (kind, connect) = read_packet(&mut stream).unwrap();
+                assert_eq!(kind, 0x10);
+                assert_eq!(&connect[..6], b"\x00\x04MQTT");
+                let client_id_len = u16::from_be_bytes([connect[10], connect[11]]) as usize;
+                let client_id = String::from_utf8_lossy(&connect[12..12 + client_id_len]).to_string();
+                let username = if connect[7] & 0x80 != 0 {
+                    let at = 12 + client_id_len;
+                    let len = u16::from_be_bytes([connect[at], connect[at + 1]]) as usize;
+                    String::from_utf8_lossy(&connect[at + 2..at + 2 + len]).to_string()
+                } else {
+                    String::new()
+                };
+                stream.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap();
+
+                while let Ok((kind, body)) = read_packet(&mut stream) {
+                    if kind >> 4 != 3 {
+                        break;
+                    }
+                    let qos = (kind >> 1) & 0x03;
+                    let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                    let topic = String::from_utf8_lossy(&body[2..2 + topic_len]).to_string();
+                    let mut at = 2 + topic_len;
+                    if qos > 0 {
+                        stream.write_all(&[0x40, 0x02, body[at], body[at + 1]]).unwrap();
+                        at += 2;
+                    }
+                    let published = Published { client_id: client_id.clone(), topic, message: body[at..].to_vec(), retain: kind & 0x01 != 0 };
+                    sender.send((username.clone(), published)).unwrap();
+                }
+            }
+        });
+
+        (port, receiver)
+    }
+
+    fn mqtt(port: u16) -> MqttConfig {
+        toml::from_str(&format!(r#"
+            host = "127.0.0.1"
+            port = {}
+            username = "desk"
+            password = "lamp"
+        "#, port)).unwrap()
+    }
+
+    fn payload(value: Value) -> HookPayload {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_publish_event() {
+        let (port, received) = broker(1);
+        let config = mqtt(port);
+        assert!(config.validate().is_ok());
+
+        let app = Config::default();
+        let hook = payload(json!({ "hook_event_name": "Stop", "session_id": "s/1+" }));
+        let data = fixtures::finished("Done");
+        let ctx = DeliveryContext { config: &app, session_id: Some("s/1+"), event: "Stop", payload: Some(&hook), inline: false };
+        MqttBackend::new(config).send(&data, &ctx).unwrap();
+
+        let (username, published) = received.recv().unwrap();
+        assert_eq!(username, "desk");
+        assert_eq!(published.topic, "claude/s_1_/Stop");
+        assert!(!published.retain);
+        let message: Value = serde_json::from_slice(&published.message).unwrap();
+        assert_eq!(message["title"], "Claude Finished");
+        assert_eq!(message["payload"]["hook_event_name"], "Stop");
+    }
+
+    #[test]
+    fn test_retained_state_follows_events() {
+        let (port, received) = broker(3);
+        let config = mqtt(port);
+        let dir = std::env::temp_dir().join(format!("claude-notifier-mqtt-{}", std::process::id()));
+        let store = SessionStore::with_base_dir(dir.clone());
+        store.store_session("s1", Default::default(), None, None).unwrap();
+
+        let events = [
+            (1_000, json!({ "hook_event_name": "PreToolUse", "session_id": "s1", "tool_name": "Bash" })),
+            (3_000, json!({ "hook_event_name": "Notification", "session_id": "s1", "message": "Claude needs your permission to use Bash" })),
+            // Delivered late, after the session had moved on, so dropped
+            (2_000, json!({ "hook_event_name": "PostToolUse", "session_id": "s1", "tool_name": "Bash" })),
+            (4_000, json!({ "hook_event_name": "SessionEnd", "session_id": "s1" })),
+        ];
+        for (at_ms, event) in events {
+            publish_state(&config, &store, &payload(event), at_ms).unwrap();
+        }
+
+        let states: Vec<Published> = received.iter().take(3).map(|(_, published)| published).collect();
+        assert!(states.iter().all(|p| p.topic == "claude/s1/state" && p.retain));
+        // Each connection has its own id, so the broker doesn't drop the others
+        assert!(states[0].client_id.starts_with("claude-notifier-"));
+        assert_ne!(states[0].client_id, states[1].client_id);
+        let first: Value = serde_json::from_slice(&states[0].message).unwrap();
+        assert_eq!(first["state"], "running_tool");
+        assert_eq!(first["tool"], "Bash");
+        assert_eq!(first["timestamp_ms"], 1_000);
+        let second: Value = serde_json::from_slice(&states[1].message).unwrap();
+        assert_eq!(second["state"], "awaiting_approval");
+        // An empty retained message removes the state
+        assert!(states[2].message.is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_state_after() {
+        let idle = payload(json!({ "hook_event_name": "Notification", "message": "Claude is waiting for your input" }));
+        assert_eq!(state_after(&idle.event), Some(SessionState::Idle));
+        let stop = payload(json!({ "hook_event_name": "Stop" }));
+        assert_eq!(state_after(&stop.event), Some(SessionState::Finished));
+        let prompt = payload(json!({ "hook_event_name": "UserPromptSubmit", "prompt": "hi" }));
+        assert_eq!(state_after(&prompt.event), Some(SessionState::Working));
+
+        let password_only: MqttConfig = toml::from_str(r#"password = "lamp""#).unwrap();
+        assert_eq!(password_only.validate().unwrap_err(), "password: needs a username");
+    }
+}
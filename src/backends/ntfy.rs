@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::webhook::{self, payload_or_stub, RetryConfig};
+use super::{DeliveryContext, NotificationBackend};
+use crate::http::Request;
+use crate::session_store::SessionStore;
+use crate::template::{Template, TemplateContext};
+use crate::types::{event_config_key, NotificationData};
+
+pub const NAME: &str = "ntfy";
+
+/// Push notifications through an ntfy server, configured under `[backends.ntfy]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NtfyConfig {
+    #[serde(default = "default_server")]
+    pub server: String,
+    #[serde(default)]
+    pub topic: String,
+    /// Access token, sent as a bearer token
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Basic auth, used when there is no token
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Priority from 1 (min) to 5 (max) by event key, over the defaults
+    #[serde(default)]
+    pub priorities: BTreeMap<String, u8>,
+    /// Tags by event key, over the defaults. Tags that name an emoji are
+    /// shown as one.
+    #[serde(default)]
+    pub tags: BTreeMap<String, Vec<String>>,
+    /// Template for the URL opened by a tap and by the action button, when
+    /// `notifications.click_behavior` is enabled, e.g. `vscode://file/{cwd}`
+    #[serde(default)]
+    pub click_url: Option<String>,
+    #[serde(flatten)]
+    pub retry: RetryConfig,
+}
+
+impl Default for NtfyConfig {
+    fn default() -> Self {
+        Self {
+            server: default_server(),
+            topic: String::new(),
+            token: None,
+            username: None,
+            password: None,
+            priorities: BTreeMap::new(),
+            tags: BTreeMap::new(),
+            click_url: None,
+            retry: RetryConfig::default(),
+        }
+    }
+}
+
+impl NtfyConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some((event, priority)) = self.priorities.iter().find(|(_, p)| !(1..=5).contains(*p)) {
+            return Err(format!("priorities.{}: {} is not between 1 and 5", event, priority));
+        }
+        if let Some(ref source) = self.click_url {
+            Template::parse(source)
+                .and_then(|template| template.validate_fields())
+                .map_err(|e| format!("click_url: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn priority(&self, event_key: &str) -> u8 {
+        self.priorities.get(event_key).copied().unwrap_or_else(|| default_priority(event_key))
+    }
+
+    fn tags(&self, event_key: &str) -> Vec<String> {
+        self.tags.get(event_key).cloned().unwrap_or_else(|| {
+            default_tags(event_key).iter().map(|tag| tag.to_string()).collect()
+        })
+    }
+}
+
+pub struct NtfyBackend {
+    config: NtfyConfig,
+}
+
+impl NtfyBackend {
+    pub fn new(config: NtfyConfig) -> Self {
+        NtfyBackend { config }
+    }
+
+    /// The JSON message published for `data`
+    fn message(&self, data: &NotificationData, ctx: &DeliveryContext) -> Value {
+        let event_key = event_config_key(ctx.event);
+        let mut message = json!({
+            "topic": self.config.topic,
+            "title": data.title,
+            "message": data.body,
+            "priority": self.config.priority(&event_key),
+            "tags": self.config.tags(&event_key),
+        });
+
+        let click = &ctx.config.notifications.click_behavior;
+        if let (true, Some(source)) = (click.enabled, &self.config.click_url) {
+            let payload = payload_or_stub(ctx);
+            let session = ctx.session_id.and_then(|id| SessionStore::open().get_session(id));
+            let template_ctx = TemplateContext {
+                payload: &payload,
+                terminal: session.as_ref().map(|s| &s.terminal_info),
+                title: &data.title,
+                body: &data.body,
+            };
+            let url = Template::parse(source)
+                .map(|template| template.render(|path| template_ctx.resolve(path)))
+                .unwrap_or_default();
+
+            if !url.is_empty() {
+                message["click"] = json!(url);
+                message["actions"] = json!([{ "action": "view", "label": click.action_label, "url": url }]);
+            }
+        }
+
+        message
+    }
+}
+
+impl NotificationBackend for NtfyBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        if self.config.topic.is_empty() {
+            return Err("backends.ntfy.topic is not set".into());
+        }
+
+        // Publishing JSON to the server root lets the title and tags carry any
+        // characters, unlike the header-based API
+        let mut request = Request::new("POST", self.config.server.trim_end_matches('/'))
+            .timeout(self.config.retry.timeout())
+            .json(&self.message(data, ctx));
+        if let Some(ref token) = self.config.token {
+            request = request.header("Authorization", &format!("Bearer {}", token)).secret();
+        } else if let Some(ref username) = self.config.username {
+            request.basic_auth = Some(format!("{}:{}", username, self.config.password.as_deref().unwrap_or("")));
+            request = request.secret();
+        }
+
+        webhook::deliver(&request, &self.config.retry, NAME, ctx)?;
+        Ok(())
+    }
+}
+
+fn default_priority(event_key: &str) -> u8 {
+    match event_key {
+        "notification" => 5,
+        "stop" | "subagent_stop" | "session_end" => 3,
+        "pre_tool_use" | "post_tool_use" | "user_prompt_submit" => 2,
+        _ => 3,
+    }
+}
+
+fn default_tags(event_key: &str) -> &'static [&'static str] {
+    match event_key {
+        "notification" => &["bell", "claude"],
+        "pre_tool_use" => &["hammer_and_wrench", "claude"],
+        "post_tool_use" => &["white_check_mark", "claude"],
+        "stop" => &["checkered_flag", "claude"],
+        "subagent_stop" => &["robot", "claude"],
+        "pre_compact" => &["package", "claude"],
+        "user_prompt_submit" => &["speech_balloon", "claude"],
+        "session_start" | "session_end" => &["computer", "claude"],
+        _ => &["claude"],
+    }
+}
+
+fn default_server() -> String { "https://ntfy.sh".to_string() }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{fixtures, stub};
+    use crate::types::Config;
+
+    fn ntfy(url: &str, extra: &str) -> NtfyConfig {
+        fixtures::config(url, &[r#"server = "{url}/""#, r#"topic = "claude""#, "spool = false", extra].join("\n"))
+    }
+
+    #[test]
+    fn test_publishes_priority_tags_and_click_action() {
+        let (url, received) = stub::serve(vec![(200, "{}".to_string())]);
+        let config = ntfy(&url, r#"
+            token = "tk_secret"
+            click_url = "vscode://file{cwd}"
+        "#);
+        assert!(config.validate().is_ok());
+
+        let app = Config::default();
+        let payload = fixtures::payload(json!({
+            "hook_event_name": "Notification",
+            "cwd": "/work/repo",
+            "message": "Claude needs your permission to use Bash"
+        }));
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Notification", payload: Some(&payload), inline: false };
+        NtfyBackend::new(config).send(&fixtures::approval("Bash: rm -rf target"), &ctx).unwrap();
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.path, "/");
+        assert_eq!(request.header("Authorization"), Some("Bearer tk_secret"));
+        let message: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(message["topic"], "claude");
+        assert_eq!(message["title"], "Claude Needs Approval");
+        assert_eq!(message["message"], "Bash: rm -rf target");
+        assert_eq!(message["priority"], 5);
+        assert_eq!(message["tags"], json!(["bell", "claude"]));
+        assert_eq!(message["click"], "vscode://file/work/repo");
+        assert_eq!(message["actions"][0]["label"], "Go to Terminal");
+        assert_eq!(message["actions"][0]["url"], "vscode://file/work/repo");
+    }
+
+    #[test]
+    fn test_basic_auth_and_overrides() {
+        let (url, received) = stub::serve(vec![(200, "{}".to_string())]);
+        let config = ntfy(&url, r#"
+            username = "phil"
+            password = "hunter2"
+            priorities = { stop = 4 }
+            tags = { stop = ["tada"] }
+        "#);
+
+        let app = Config::default();
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Stop", payload: None, inline: false };
+        NtfyBackend::new(config).send(&fixtures::approval("Bash: rm -rf target"), &ctx).unwrap();
+
+        let request = received.recv().unwrap();
+        // base64("phil:hunter2")
+        assert_eq!(request.header("Authorization"), Some("Basic cGhpbDpodW50ZXIy"));
+        let message: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(message["priority"], 4);
+        assert_eq!(message["tags"], json!(["tada"]));
+        assert!(message.get("click").is_none());
+
+        assert!(ntfy(&url, "priorities = { stop = 9 }").validate().is_err());
+    }
+}
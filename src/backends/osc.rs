@@ -0,0 +1,201 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{DeliveryContext, NotificationBackend};
+use crate::session_store::SessionStore;
+use crate::terminal_detector::TerminalInfo;
+use crate::types::NotificationData;
+
+pub const NAME: &str = "osc";
+
+/// Notifications raised by the terminal itself through OSC escape sequences,
+/// configured under `[backends.osc]`. They travel over SSH like any other
+/// output, so they reach the local terminal from remote hosts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OscConfig {
+    #[serde(default)]
+    pub protocol: OscProtocol,
+    /// Wrap sequences so tmux hands them to the outer terminal. Needs
+    /// `set -g allow-passthrough on` in tmux 3.3 and later.
+    #[serde(default = "default_tmux_passthrough")]
+    pub tmux_passthrough: bool,
+    /// Write here instead of the session's terminal
+    #[serde(default)]
+    pub tty: Option<String>,
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        Self {
+            protocol: OscProtocol::default(),
+            tmux_passthrough: default_tmux_passthrough(),
+            tty: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OscProtocol {
+    /// Pick by the detected terminal, falling back to OSC 9
+    #[default]
+    Auto,
+    /// iTerm2, WezTerm, Windows Terminal, Ghostty; body only
+    Osc9,
+    /// rxvt-unicode, foot, Ghostty; title and body
+    Osc777,
+    /// kitty; title and body
+    Osc99,
+}
+
+impl OscProtocol {
+    fn resolve(self, terminal_app: Option<&str>) -> Self {
+        if self != OscProtocol::Auto {
+            return self;
+        }
+        match terminal_app.map(str::to_lowercase).as_deref() {
+            Some("kitty") => OscProtocol::Osc99,
+            Some("foot" | "ghostty" | "rxvt" | "urxvt") => OscProtocol::Osc777,
+            _ => OscProtocol::Osc9,
+        }
+    }
+}
+
+pub struct OscBackend {
+    config: OscConfig,
+    store: SessionStore,
+}
+
+impl OscBackend {
+    pub fn new(config: OscConfig) -> Self {
+        Self::with_store(config, SessionStore::open())
+    }
+
+    /// A backend that looks up the session's terminal in `store`
+    pub fn with_store(config: OscConfig, store: SessionStore) -> Self {
+        OscBackend { config, store }
+    }
+}
+
+impl NotificationBackend for OscBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        let terminal = ctx.session_id
+            .and_then(|id| self.store.get_session(id))
+            .map(|session| session.terminal_info)
+            .unwrap_or_default();
+        let tty = self.config.tty.as_ref().or(terminal.tty.as_ref())
+            .ok_or("no terminal recorded for the session")?;
+
+        let sequence = sequence(&self.config, &terminal, data);
+        // A suspended or stuck terminal stops draining output, which must not
+        // hang delivery. Detached processes have no controlling terminal, and
+        // opening the session's tty mustn't make it theirs.
+        let mut device = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK | libc::O_NOCTTY)
+            .open(tty)
+            .map_err(|e| format!("Failed to open {}: {}", tty, e))?;
+        match device.write_all(sequence.as_bytes()) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Err(format!("{} isn't accepting output", tty).into()),
+            result => Ok(result?),
+        }
+    }
+}
+
+/// The bytes that raise `data` as a notification in `terminal`
+pub fn sequence(config: &OscConfig, terminal: &TerminalInfo, data: &NotificationData) -> String {
+    let title = clean(&data.title);
+    let body = clean(&data.body);
+
+    let sequence = match config.protocol.resolve(terminal.terminal_app.as_deref()) {
+        OscProtocol::Osc777 => format!("\x1b]777;notify;{};{}\x1b\\", title.replace(';', ","), body),
+        // Title and body are sent as parts of one notification, finished by d=1
+        OscProtocol::Osc99 => format!(
+            "\x1b]99;i=claude:d=0:p=title;{}\x1b\\\x1b]99;i=claude:d=1:p=body;{}\x1b\\",
+            title, body
+        ),
+        OscProtocol::Osc9 | OscProtocol::Auto => format!("\x1b]9;{}: {}\x1b\\", title, body),
+    };
+
+    if config.tmux_passthrough && terminal.tmux_pane.is_some() {
+        tmux_passthrough(&sequence)
+    } else {
+        sequence
+    }
+}
+
+/// Wrap `sequence` in tmux's DCS passthrough, doubling the escapes inside it
+fn tmux_passthrough(sequence: &str) -> String {
+    format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+}
+
+/// Keep text from ending the sequence early or injecting its own
+fn clean(text: &str) -> String {
+    text.chars()
+        .map(|c| if c == '\n' || c == '\t' { ' ' } else { c })
+        .filter(|c| !c.is_control())
+        .collect()
+}
+
+fn default_tmux_passthrough() -> bool { true }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Config;
+
+    fn data(title: &str, body: &str) -> NotificationData {
+        NotificationData { title: title.to_string(), body: body.to_string(), sound: String::new() }
+    }
+
+    #[test]
+    fn test_sequences_by_terminal() {
+        let config = OscConfig::default();
+        let finished = data("Claude; Finished", "Fixed it\nand\x07 tested");
+
+        let ghostty = TerminalInfo { terminal_app: Some("ghostty".to_string()), ..Default::default() };
+        assert_eq!(sequence(&config, &ghostty, &finished), "\x1b]777;notify;Claude, Finished;Fixed it and tested\x1b\\");
+
+        let kitty = TerminalInfo { terminal_app: Some("Kitty".to_string()), ..Default::default() };
+        assert_eq!(
+            sequence(&config, &kitty, &finished),
+            "\x1b]99;i=claude:d=0:p=title;Claude; Finished\x1b\\\x1b]99;i=claude:d=1:p=body;Fixed it and tested\x1b\\"
+        );
+
+        // Unknown terminals, and anything seen only through tmux, get OSC 9 passed through
+        let tmux = TerminalInfo { terminal_app: Some("tmux".to_string()), tmux_pane: Some("%1".to_string()), ..Default::default() };
+        assert_eq!(
+            sequence(&config, &tmux, &data("Claude Finished", "Done")),
+            "\x1bPtmux;\x1b\x1b]9;Claude Finished: Done\x1b\x1b\\\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_writes_to_the_sessions_terminal() {
+        let tty = std::env::temp_dir().join(format!("claude-notifier-osc-{}", std::process::id()));
+        std::fs::write(&tty, "").unwrap();
+
+        let dir = std::env::temp_dir().join(format!("claude-notifier-osc-sessions-{}", std::process::id()));
+        let terminal = TerminalInfo { tty: Some(tty.to_string_lossy().to_string()), ..Default::default() };
+        SessionStore::with_base_dir(dir.clone()).store_session("osc", terminal, None, None).unwrap();
+
+        let config: OscConfig = toml::from_str(r#"protocol = "osc777""#).unwrap();
+        let backend = OscBackend::with_store(config, SessionStore::with_base_dir(dir.clone()));
+        let app = Config::default();
+        let ctx = DeliveryContext { config: &app, session_id: Some("osc"), event: "Stop", payload: None, inline: false };
+        backend.send(&data("Claude Finished", "Done"), &ctx).unwrap();
+        assert_eq!(std::fs::read_to_string(&tty).unwrap(), "\x1b]777;notify;Claude Finished;Done\x1b\\");
+
+        let unknown = DeliveryContext { session_id: None, ..ctx };
+        assert!(backend.send(&data("Claude Finished", "Done"), &unknown).is_err());
+        std::fs::remove_file(&tty).ok();
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::webhook::{self, RetryConfig};
+use super::{DeliveryContext, NotificationBackend};
+use crate::http::Request;
+use crate::session_store::SessionStore;
+use crate::types::{event_config_key, NotificationData};
+
+pub const NAME: &str = "pushover";
+
+/// Push notifications through Pushover, configured under `[backends.pushover]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PushoverConfig {
+    /// The application's API token
+    #[serde(default)]
+    pub token: String,
+    /// User or group key
+    #[serde(default)]
+    pub user: String,
+    /// Send to this device only instead of all of the user's devices
+    #[serde(default)]
+    pub device: Option<String>,
+    #[serde(default = "default_api_base")]
+    pub api_base: String,
+    /// Priority from -2 (lowest) to 2 (emergency) by event key, over the defaults
+    #[serde(default)]
+    pub priorities: BTreeMap<String, i8>,
+    /// Pushover sound by `notifications.sounds` name, over the defaults
+    #[serde(default)]
+    pub sounds: BTreeMap<String, String>,
+    /// Send approval requests at emergency priority, repeating until
+    /// acknowledged or the session moves on
+    #[serde(default)]
+    pub emergency: bool,
+    /// Seconds between emergency repeats, at least 30
+    #[serde(default = "default_emergency_retry_secs")]
+    pub emergency_retry_secs: u32,
+    /// Seconds before emergency repeats stop, at most 10800
+    #[serde(default = "default_emergency_expire_secs")]
+    pub emergency_expire_secs: u32,
+    /// Timeouts and retries. `spool` and `spool_dir` have no effect, since
+    /// every request holds the application token.
+    #[serde(flatten)]
+    pub retry: RetryConfig,
+}
+
+impl Default for PushoverConfig {
+    fn default() -> Self {
+        Self {
+            token: String::new(),
+            user: String::new(),
+            device: None,
+            api_base: default_api_base(),
+            priorities: BTreeMap::new(),
+            sounds: BTreeMap::new(),
+            emergency: false,
+            emergency_retry_secs: default_emergency_retry_secs(),
+            emergency_expire_secs: default_emergency_expire_secs(),
+            retry: RetryConfig::default(),
+        }
+    }
+}
+
+impl PushoverConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some((event, priority)) = self.priorities.iter().find(|(_, p)| !(-2..=2).contains(*p)) {
+            return Err(format!("priorities.{}: {} is not between -2 and 2", event, priority));
+        }
+        if self.emergency_retry_secs < 30 {
+            return Err("emergency_retry_secs must be at least 30".to_string());
+        }
+        if self.emergency_expire_secs > 10800 {
+            return Err("emergency_expire_secs can be at most 10800".to_string());
+        }
+        Ok(())
+    }
+
+    /// The priority for an event; `idle` marks Claude's notice that it's
+    /// waiting for a prompt, which nothing is blocked on
+    fn priority(&self, event_key: &str, idle: bool) -> i8 {
+        if self.emergency && event_key == "notification" && !idle {
+            return 2;
+        }
+        self.priorities.get(event_key).copied().unwrap_or_else(|| default_priority(event_key))
+    }
+
+    /// The Pushover sound for a macOS sound name; an empty name means silent
+    fn sound(&self, name: &str) -> Option<String> {
+        if name.is_empty() {
+            return Some("none".to_string());
+        }
+        self.sounds.get(name).cloned().or_else(|| default_sound(name).map(String::from))
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/1/{}", self.api_base.trim_end_matches('/'), path)
+    }
+}
+
+pub struct PushoverBackend {
+    config: PushoverConfig,
+    store: SessionStore,
+}
+
+impl PushoverBackend {
+    pub fn new(config: PushoverConfig) -> Self {
+        Self::with_store(config, SessionStore::open())
+    }
+
+    /// A backend that records emergency receipts in `store`
+    pub fn with_store(config: PushoverConfig, store: SessionStore) -> Self {
+        PushoverBackend { config, store }
+    }
+
+    /// The message for `data`, as the JSON the messages API accepts
+    fn message(&self, data: &NotificationData, ctx: &DeliveryContext) -> Value {
+        let idle = ctx.payload.is_some_and(|payload| payload.event.is_idle_notice());
+        let priority = self.config.priority(&event_config_key(ctx.event), idle);
+        let mut message = json!({
+            "token": self.config.token,
+            "user": self.config.user,
+            "title": data.title,
+            "message": data.body,
+            "priority": priority,
+        });
+
+        if let Some(sound) = self.config.sound(&data.sound) {
+            message["sound"] = json!(sound);
+        }
+        if let Some(ref device) = self.config.device {
+            message["device"] = json!(device);
+        }
+        if priority == 2 {
+            message["retry"] = json!(self.config.emergency_retry_secs);
+            message["expire"] = json!(self.config.emergency_expire_secs);
+        }
+        message
+    }
+}
+
+impl NotificationBackend for PushoverBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        if self.config.token.is_empty() || self.config.user.is_empty() {
+            return Err("backends.pushover.token and user must be set".into());
+        }
+
+        let message = self.message(data, ctx);
+        let request = Request::new("POST", &self.config.url("messages.json"))
+            .timeout(self.config.retry.timeout())
+            .json(&message)
+            .secret();
+        let response = webhook::deliver(&request, &self.config.retry, NAME, ctx)?;
+
+        // Emergency messages repeat until the session moves on and cancels them
+        if let (2, Some(session_id)) = (message["priority"].as_i64().unwrap_or(0), ctx.session_id) {
+            let receipt = serde_json::from_str::<Value>(&response.body).ok()
+                .and_then(|body| body["receipt"].as_str().map(String::from));
+            if let Some(receipt) = receipt {
+                self.store.update_session(session_id, |session| session.pushover_receipt = Some(receipt));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Stop the emergency repeats of the session's approval request, if it has
+/// one outstanding
+pub fn cancel_emergency(config: &PushoverConfig, store: &SessionStore, session_id: &str) {
+    let outstanding = store.get_session(session_id).is_some_and(|session| session.pushover_receipt.is_some());
+    if !outstanding {
+        return;
+    }
+    let Some(Some(receipt)) = store.update_session(session_id, |session| session.pushover_receipt.take()) else {
+        return;
+    };
+
+    let url = config.url(&format!("receipts/{}/cancel.json", receipt));
+    let request = Request::new("POST", &url)
+        .timeout(config.retry.timeout())
+        .json(&json!({ "token": config.token }));
+    request.send().ok();
+}
+
+fn default_priority(event_key: &str) -> i8 {
+    match event_key {
+        "notification" => 1,
+        "pre_tool_use" | "post_tool_use" | "user_prompt_submit" => -1,
+        _ => 0,
+    }
+}
+
+fn default_sound(name: &str) -> Option<&'static str> {
+    match name {
+        "Glass" => Some("magic"),
+        "Pop" => Some("pianobar"),
+        "Hero" => Some("classical"),
+        "Tink" => Some("bike"),
+        "Morse" => Some("mechanical"),
+        "Basso" => Some("falling"),
+        "Purr" => Some("cosmic"),
+        "Sosumi" => Some("siren"),
+        _ => None,
+    }
+}
+
+fn default_api_base() -> String { "https://api.pushover.net".to_string() }
+fn default_emergency_retry_secs() -> u32 { 60 }
+fn default_emergency_expire_secs() -> u32 { 3600 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{fixtures, stub};
+    use crate::types::Config;
+
+    fn pushover(url: &str) -> PushoverConfig {
+        fixtures::config(url, r#"
+            token = "app-token"
+            user = "user-key"
+            api_base = "{url}"
+            emergency = true
+            spool = false
+            sounds = { Hero = "tugboat" }
+        "#)
+    }
+
+    #[test]
+    fn test_emergency_approval_then_cancelled() {
+        let (url, received) = stub::serve(vec![
+            (200, r#"{"status":1,"receipt":"r123"}"#.to_string()),
+            (200, "{}".to_string()),
+        ]);
+        let config = pushover(&url);
+        assert!(config.validate().is_ok());
+        let dir = std::env::temp_dir().join(format!("claude-notifier-pushover-{}", std::process::id()));
+        let store = SessionStore::with_base_dir(dir.clone());
+        store.store_session("s1", Default::default(), None, None).unwrap();
+        let backend = PushoverBackend::with_store(config.clone(), SessionStore::with_base_dir(dir.clone()));
+        let app = Config::default();
+
+        let approval = fixtures::approval("Bash");
+        let ctx = DeliveryContext { config: &app, session_id: Some("s1"), event: "Notification", payload: None, inline: false };
+        backend.send(&approval, &ctx).unwrap();
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.path, "/1/messages.json");
+        let message: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(message["priority"], 2);
+        assert_eq!(message["retry"], 60);
+        assert_eq!(message["expire"], 3600);
+        assert_eq!(message["sound"], "magic");
+        assert_eq!(store.get_session("s1").unwrap().pushover_receipt.as_deref(), Some("r123"));
+
+        // Cancelled once, then there's nothing outstanding to cancel
+        cancel_emergency(&config, &store, "s1");
+        cancel_emergency(&config, &store, "s1");
+        assert_eq!(received.recv().unwrap().path, "/1/receipts/r123/cancel.json");
+        assert!(store.get_session("s1").unwrap().pushover_receipt.is_none());
+
+        let finished = fixtures::finished("Done");
+        let message = backend.message(&finished, &DeliveryContext { event: "Stop", ..ctx });
+        assert_eq!(message["priority"], 0);
+        assert_eq!(message["sound"], "tugboat");
+        assert!(message.get("retry").is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_priorities_and_silence() {
+        let config = pushover("http://localhost");
+        assert_eq!(config.priority("pre_tool_use", false), -1);
+        assert_eq!(config.sound(""), Some("none".to_string()));
+        assert_eq!(config.sound("Funk"), None);
+
+        let mut bad = config.clone();
+        bad.priorities.insert("stop".to_string(), 3);
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_idle_notice_is_not_an_emergency() {
+        let config = pushover("http://localhost");
+        let backend = PushoverBackend::with_store(config, SessionStore::with_base_dir(std::env::temp_dir()));
+        let app = Config::default();
+        let idle = fixtures::payload(json!({ "hook_event_name": "Notification", "message": "Claude is waiting for your input" }));
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Notification", payload: Some(&idle), inline: false };
+
+        let message = backend.message(&fixtures::approval("Bash"), &ctx);
+        assert_eq!(message["priority"], 1);
+        assert!(message.get("retry").is_none());
+    }
+}
@@ -0,0 +1,97 @@
+use serde_json::{json, Value};
+
+use super::chat::{self, escape_html, ChatConfig, ChatMessage};
+use super::{DeliveryContext, NotificationBackend};
+use crate::template::truncate;
+use crate::types::NotificationData;
+
+pub const NAME: &str = "slack";
+
+/// Slack incoming webhooks, formatted with Block Kit
+pub struct SlackBackend {
+    config: ChatConfig,
+}
+
+impl SlackBackend {
+    pub fn new(config: ChatConfig) -> Self {
+        SlackBackend { config }
+    }
+}
+
+impl NotificationBackend for SlackBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        chat::post(NAME, &self.config, data, ctx, format)
+    }
+}
+
+/// Blocks inside a legacy attachment, the only way to give them a colour bar
+pub fn format(message: &ChatMessage) -> Value {
+    let mut blocks = vec![
+        json!({ "type": "header", "text": { "type": "plain_text", "text": truncate(&message.title, 150) } }),
+        json!({ "type": "section", "text": { "type": "mrkdwn", "text": truncate(&escape_html(&message.body), 3000) } }),
+    ];
+
+    let context: Vec<Value> = message.fields().into_iter()
+        .map(|(label, value)| json!({ "type": "mrkdwn", "text": format!("*{}:* {}", label, escape_html(value)) }))
+        .collect();
+    if !context.is_empty() {
+        blocks.push(json!({ "type": "context", "elements": context }));
+    }
+
+    json!({
+        // Shown in push notifications and clients without Block Kit
+        "text": escape_html(&format!("{}: {}", message.title, message.body)),
+        "attachments": [{
+            "color": format!("#{:06x}", message.color),
+            "blocks": blocks,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{fixtures, stub};
+    use crate::types::Config;
+
+    #[test]
+    fn test_block_kit_message_per_channel() {
+        let (url, received) = stub::serve(vec![(200, "ok".to_string())]);
+        let config: ChatConfig = fixtures::config(&url, r#"
+            spool = false
+            [[channels]]
+            url = "{url}/team"
+            events = ["Stop"]
+            [[channels]]
+            url = "{url}/alerts"
+            events = ["Notification"]
+        "#);
+
+        let app = Config::default();
+        let data = fixtures::finished("Fixed <the> bug & shipped");
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Stop", payload: None, inline: false };
+        SlackBackend::new(config.clone()).send(&data, &ctx).unwrap();
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.path, "/team");
+        let body: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["text"], "Claude Finished: Fixed &lt;the&gt; bug &amp; shipped");
+        let attachment = &body["attachments"][0];
+        assert_eq!(attachment["color"], "#2eb67d");
+        assert_eq!(attachment["blocks"][0]["text"]["text"], "Claude Finished");
+        assert_eq!(attachment["blocks"][1]["text"]["text"], "Fixed &lt;the&gt; bug &amp; shipped");
+        // Only the one channel forwarding Stop was posted to
+        assert!(received.recv().is_err());
+
+        // Limits count the ellipsis, and the fallback text can't ping anyone
+        let long = NotificationData { title: "t".repeat(151), body: "<!channel>".to_string(), ..data };
+        let body = format(&ChatMessage::new(&long, &ctx, &config));
+        let header = body["attachments"][0]["blocks"][0]["text"]["text"].as_str().unwrap();
+        assert_eq!(header.chars().count(), 150);
+        assert!(body["text"].as_str().unwrap().ends_with(": &lt;!channel&gt;"));
+    }
+}
@@ -0,0 +1,458 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::chat::{ChatConfig, ChatMessage};
+use super::{DeliveryContext, NotificationBackend};
+use crate::http::{quote, run_curl};
+use crate::rules::glob_match;
+use crate::session_store::{HistoryEntry, SessionInfo, SessionStore};
+use crate::template::format_timestamp;
+use crate::types::NotificationData;
+
+pub const NAME: &str = "smtp";
+
+/// History entries kept per session between digests
+const HISTORY_LIMIT: usize = 500;
+
+/// Email through an SMTP server, configured under `[backends.smtp]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SmtpConfig {
+    #[serde(default)]
+    pub host: String,
+    /// Defaults to 587 for STARTTLS, 465 for TLS and 25 for plain
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub from: String,
+    #[serde(default)]
+    pub to: Vec<String>,
+    /// Events sent as an email each, as globs
+    #[serde(default = "default_events")]
+    pub events: Vec<String>,
+    /// Events that send a digest of everything the session did since the
+    /// last one, as globs. Empty disables digests.
+    #[serde(default)]
+    pub digest_on: Vec<String>,
+    #[serde(default = "default_subject_prefix")]
+    pub subject_prefix: String,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: None,
+            security: SmtpSecurity::default(),
+            username: None,
+            password: None,
+            from: String::new(),
+            to: Vec::new(),
+            events: default_events(),
+            digest_on: Vec::new(),
+            subject_prefix: default_subject_prefix(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Upgrade the connection with STARTTLS, and fail if the server can't
+    #[default]
+    Starttls,
+    /// TLS from the start (SMTPS)
+    Tls,
+    /// No encryption, for local relays
+    Plain,
+}
+
+impl SmtpConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.host.is_empty() && (self.from.is_empty() || self.to.is_empty()) {
+            return Err("from and to are required when host is set".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether hook events need recording for digests
+    pub fn wants_history(&self) -> bool {
+        !self.host.is_empty() && !self.digest_on.is_empty()
+    }
+
+    fn url(&self) -> String {
+        let (scheme, default_port) = match self.security {
+            SmtpSecurity::Starttls => ("smtp", 587),
+            SmtpSecurity::Tls => ("smtps", 465),
+            SmtpSecurity::Plain => ("smtp", 25),
+        };
+        format!("{}://{}:{}", scheme, self.host, self.port.unwrap_or(default_port))
+    }
+}
+
+pub struct SmtpBackend {
+    config: SmtpConfig,
+    store: SessionStore,
+}
+
+impl SmtpBackend {
+    pub fn new(config: SmtpConfig) -> Self {
+        Self::with_store(config, SessionStore::open())
+    }
+
+    /// A backend that reads digests from the history in `store`
+    pub fn with_store(config: SmtpConfig, store: SessionStore) -> Self {
+        SmtpBackend { config, store }
+    }
+
+    /// Send one email through curl
+    fn send_mail(&self, subject: &str, body: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        let message = build_message(&self.config.from, &self.config.to, subject, body, now.as_secs(), now.as_nanos());
+
+        // The config goes on curl's stdin, so the message needs a file of its own
+        let path = std::env::temp_dir().join(format!("claude-notifier-mail-{}-{}.eml", std::process::id(), now.as_nanos()));
+        let mut file = OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path)?;
+        file.write_all(message.as_bytes())?;
+        drop(file);
+
+        let mut lines = vec![
+            format!("url = {}", quote(&self.config.url())),
+            format!("mail-from = {}", quote(&self.config.from)),
+            format!("upload-file = {}", quote(&path.to_string_lossy())),
+            format!("max-time = {}", self.config.timeout_secs),
+            // Converts line endings to CRLF; curl also escapes leading dots
+            "crlf".to_string(),
+        ];
+        for recipient in &self.config.to {
+            lines.push(format!("mail-rcpt = {}", quote(recipient)));
+        }
+        if self.config.security == SmtpSecurity::Starttls {
+            lines.push("ssl-reqd".to_string());
+        }
+        if let Some(ref username) = self.config.username {
+            let credentials = format!("{}:{}", username, self.config.password.as_deref().unwrap_or(""));
+            lines.push(format!("user = {}", quote(&credentials)));
+        }
+        lines.push(String::new());
+
+        let result = run_curl(&lines.join("\n"));
+        fs::remove_file(&path).ok();
+        result.map_err(|e| format!("Sending mail through {} failed: {}", self.config.host, e))?;
+        Ok(())
+    }
+
+    fn send_digest(&self, session_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(session) = self.store.get_session(session_id) else { return Ok(()) };
+        if session.history.is_empty() {
+            return Ok(());
+        }
+
+        let (subject, body) = digest(&session, &self.config.subject_prefix);
+        self.send_mail(&subject, &body)?;
+
+        // Only forget what was sent; events may have arrived in the meantime
+        let sent = session.history.len();
+        self.store.update_session(session_id, |session| {
+            session.history.drain(..sent.min(session.history.len()));
+        });
+        Ok(())
+    }
+}
+
+impl NotificationBackend for SmtpBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        if self.config.host.is_empty() {
+            return Err("backends.smtp.host is not set".into());
+        }
+
+        let matches = |patterns: &[String]| patterns.iter().any(|pattern| glob_match(pattern, ctx.event));
+        if let (true, Some(session_id)) = (matches(&self.config.digest_on), ctx.session_id) {
+            return self.send_digest(session_id);
+        }
+        if !matches(&self.config.events) {
+            return Ok(());
+        }
+
+        let subject = format!("{} {}", self.config.subject_prefix, data.title);
+        let message = ChatMessage::new(data, ctx, &ChatConfig::default());
+        let mut body = format!("{}\n\nEvent: {}\n", data.body, ctx.event);
+        for (label, value) in message.fields() {
+            body.push_str(&format!("{}: {}\n", label, value));
+        }
+        self.send_mail(subject.trim(), &body)
+    }
+}
+
+/// Remember a notified event for the session's next digest
+pub fn record(store: &SessionStore, session_id: &str, event: &str, data: &NotificationData, now_ms: u64) {
+    store.update_session(session_id, |session| {
+        session.history.push(HistoryEntry {
+            at_ms: now_ms,
+            event: event.to_string(),
+            title: data.title.clone(),
+            body: data.body.clone(),
+        });
+        let excess = session.history.len().saturating_sub(HISTORY_LIMIT);
+        session.history.drain(..excess);
+    });
+}
+
+/// Subject and body of a digest of the session's history
+fn digest(session: &SessionInfo, subject_prefix: &str) -> (String, String) {
+    let directory = session.terminal_info.project_dir.as_deref().or(session.cwd.as_deref());
+    let project = directory
+        .and_then(|dir| std::path::Path::new(dir).file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| session.session_id.clone());
+
+    let subject = format!("{} Session summary: {}", subject_prefix, project);
+    let mut body = format!("Claude Code session {}\n", session.session_id);
+    if let Some(directory) = directory {
+        body.push_str(&format!("Directory: {}\n", directory));
+    }
+    if let Some(ref terminal) = session.terminal_info.terminal_app {
+        body.push_str(&format!("Terminal: {}\n", terminal));
+    }
+    body.push('\n');
+
+    for entry in &session.history {
+        let timestamp = format_timestamp(entry.at_ms / 1000);
+        body.push_str(&format!("{}  {}: {}\n", &timestamp[11..19], entry.title, entry.body));
+    }
+    body.push_str(&format!("\n{} events, times in UTC\n", session.history.len()));
+
+    (subject.trim().to_string(), body)
+}
+
+/// An RFC 5322 message with a UTF-8 plain text body
+fn build_message(from: &str, to: &[String], subject: &str, body: &str, now_secs: u64, nonce: u128) -> String {
+    let header = |value: &str| value.replace(['\r', '\n'], " ");
+    format!(
+        "From: {}\nTo: {}\nSubject: {}\nDate: {}\nMessage-ID: <{}.{}@claude-notifier>\nMIME-Version: 1.0\n\
+         Content-Type: text/plain; charset=utf-8\nContent-Transfer-Encoding: 8bit\n\n{}",
+        header(from),
+        header(&to.join(", ")),
+        encode_subject(&header(subject)),
+        rfc2822_date(now_secs),
+        nonce,
+        std::process::id(),
+        body
+    )
+}
+
+/// Subjects must be ASCII; anything else goes in an RFC 2047 encoded word
+fn encode_subject(subject: &str) -> String {
+    if subject.is_ascii() {
+        subject.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", base64(subject.as_bytes()))
+    }
+}
+
+fn rfc2822_date(secs: u64) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    // Reuse the civil date maths behind the RFC 3339 timestamps
+    let timestamp = format_timestamp(secs);
+    let month: usize = timestamp[5..7].parse().unwrap_or(1);
+    format!(
+        "{}, {} {} {} {} +0000",
+        DAYS[(secs / 86400 % 7) as usize],
+        &timestamp[8..10],
+        MONTHS[month - 1],
+        &timestamp[0..4],
+        &timestamp[11..19]
+    )
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn default_events() -> Vec<String> { vec!["*".to_string()] }
+fn default_subject_prefix() -> String { "[Claude]".to_string() }
+fn default_timeout_secs() -> u64 { 30 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal_detector::TerminalInfo;
+    use crate::types::Config;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+
+    /// A mail as the sink received it
+    struct Mail {
+        auth: Option<String>,
+        from: String,
+        to: Vec<String>,
+        data: String,
+    }
+
+    /// Accept one SMTP session, without TLS, and hand back the mail
+    fn sink() -> (u16, Receiver<Mail>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut mail = Mail { auth: None, from: String::new(), to: Vec::new(), data: String::new() };
+            writer.write_all(b"220 sink ESMTP\r\n").unwrap();
+
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                let command = line.trim_end().to_string();
+                line.clear();
+                let reply: &[u8] = if command.starts_with("EHLO") {
+                    b"250-sink\r\n250 AUTH PLAIN\r\n"
+                } else if let Some(initial) = command.strip_prefix("AUTH PLAIN") {
+                    // The credentials come with the command or after a 334
+                    let mut auth = initial.trim().to_string();
+                    if auth.is_empty() {
+                        writer.write_all(b"334 \r\n").unwrap();
+                        reader.read_line(&mut auth).unwrap();
+                    }
+                    mail.auth = Some(auth.trim().to_string());
+                    b"235 ok\r\n"
+                } else if let Some(from) = command.strip_prefix("MAIL FROM:") {
+                    mail.from = from.to_string();
+                    b"250 ok\r\n"
+                } else if let Some(to) = command.strip_prefix("RCPT TO:") {
+                    mail.to.push(to.to_string());
+                    b"250 ok\r\n"
+                } else if command == "DATA" {
+                    writer.write_all(b"354 go ahead\r\n").unwrap();
+                    loop {
+                        let mut data = String::new();
+                        reader.read_line(&mut data).unwrap();
+                        if data == ".\r\n" {
+                            break;
+                        }
+                        mail.data.push_str(&data);
+                    }
+                    b"250 queued\r\n"
+                } else if command == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).unwrap();
+            }
+            sender.send(mail).ok();
+        });
+
+        (port, receiver)
+    }
+
+    fn smtp(port: u16, extra: &str) -> SmtpConfig {
+        toml::from_str(&format!(r#"
+            host = "127.0.0.1"
+            port = {}
+            security = "plain"
+            from = "claude@example.com"
+            to = ["me@example.com", "team@example.com"]
+            {}
+        "#, port, extra)).unwrap()
+    }
+
+    #[test]
+    fn test_event_mail_with_auth() {
+        let (port, received) = sink();
+        let config = smtp(port, r#"
+            username = "me"
+            password = "hunter2"
+        "#);
+        assert!(config.validate().is_ok());
+
+        let app = Config::default();
+        let data = NotificationData { title: "Claude Finished ✓".to_string(), body: "All tests pass\n.dotted line".to_string(), sound: "Hero".to_string() };
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Stop", payload: None, inline: false };
+        SmtpBackend::new(config).send(&data, &ctx).unwrap();
+
+        let mail = received.recv().unwrap();
+        // base64("\0me\0hunter2")
+        assert_eq!(mail.auth.as_deref(), Some("AG1lAGh1bnRlcjI="));
+        assert_eq!(mail.from, "<claude@example.com>");
+        assert_eq!(mail.to, vec!["<me@example.com>", "<team@example.com>"]);
+        assert!(mail.data.contains("To: me@example.com, team@example.com\r\n"));
+        assert!(mail.data.contains(&format!("Subject: =?UTF-8?B?{}?=\r\n", base64("[Claude] Claude Finished ✓".as_bytes()))));
+        assert!(mail.data.contains("\r\n\r\nAll tests pass\r\n..dotted line\r\n\r\nEvent: Stop\r\n"));
+    }
+
+    #[test]
+    fn test_digest_from_session_history() {
+        let (port, received) = sink();
+        let config = smtp(port, r#"
+            events = []
+            digest_on = ["Stop"]
+        "#);
+        assert!(config.wants_history());
+
+        let session_id = "smtp-digest";
+        let dir = std::env::temp_dir().join(format!("claude-notifier-smtp-{}", std::process::id()));
+        let store = SessionStore::with_base_dir(dir.clone());
+        let terminal = TerminalInfo { project_dir: Some("/work/repo".to_string()), ..Default::default() };
+        store.store_session(session_id, terminal, None, None).unwrap();
+        let event = |title: &str, body: &str| NotificationData { title: title.to_string(), body: body.to_string(), sound: String::new() };
+        record(&store, session_id, "PreToolUse", &event("Claude Tool Use", "Running: cargo test"), 3_600_000);
+        record(&store, session_id, "Stop", &event("Claude Finished", "Done"), 3_661_000);
+
+        let app = Config::default();
+        let ctx = DeliveryContext { config: &app, session_id: Some(session_id), event: "Stop", payload: None, inline: false };
+        SmtpBackend::with_store(config, SessionStore::with_base_dir(dir.clone())).send(&event("Claude Finished", "Done"), &ctx).unwrap();
+
+        let mail = received.recv().unwrap();
+        assert!(mail.data.contains("Subject: [Claude] Session summary: repo\r\n"));
+        assert!(mail.data.contains("01:00:00  Claude Tool Use: Running: cargo test\r\n"));
+        assert!(mail.data.contains("01:01:01  Claude Finished: Done\r\n"));
+        assert!(mail.data.contains("2 events, times in UTC"));
+        assert!(store.get_session(session_id).unwrap().history.is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_encoding_helpers() {
+        assert_eq!(base64(b"hello"), "aGVsbG8=");
+        assert_eq!(base64(b"hi!"), "aGkh");
+        assert_eq!(rfc2822_date(0), "Thu, 01 Jan 1970 00:00:00 +0000");
+        assert_eq!(rfc2822_date(1_792_281_600), "Sun, 18 Oct 2026 00:00:00 +0000");
+    }
+}
@@ -0,0 +1,470 @@
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::chat::{escape_html, ChatConfig, ChatMessage};
+use super::webhook::{self, RetryConfig};
+use super::{DeliveryContext, NotificationBackend};
+use crate::daemon;
+use crate::http::Request;
+use crate::lock::FileLock;
+use crate::private_dir;
+use crate::rules::glob_match;
+use crate::types::NotificationData;
+
+pub const NAME: &str = "telegram";
+
+/// Messages through a Telegram bot, configured under `[backends.telegram]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelegramConfig {
+    /// The bot token from @BotFather
+    #[serde(default)]
+    pub token: String,
+    /// Numeric chat id, or `@channel` for a public channel
+    #[serde(default)]
+    pub chat_id: Option<ChatId>,
+    #[serde(default = "default_api_base")]
+    pub api_base: String,
+    /// Events sent as messages, as globs
+    #[serde(default = "default_events")]
+    pub events: Vec<String>,
+    /// Telegram user ids allowed to answer approval requests. If empty, only
+    /// the user of a private chat with the bot can.
+    #[serde(default)]
+    pub allowed_users: Vec<i64>,
+    /// Where pending approvals and the update offset are kept; a directory
+    /// only the current user can access under `$XDG_RUNTIME_DIR` by default
+    #[serde(default)]
+    pub state_dir: Option<String>,
+    /// Timeouts and retries for sending. `spool` and `spool_dir` have no
+    /// effect: failed messages are never spooled, since the request holds
+    /// the bot token.
+    #[serde(flatten)]
+    pub retry: RetryConfig,
+}
+
+impl Default for TelegramConfig {
+    fn default() -> Self {
+        Self {
+            token: String::new(),
+            chat_id: None,
+            api_base: default_api_base(),
+            events: default_events(),
+            allowed_users: Vec::new(),
+            state_dir: None,
+            retry: RetryConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ChatId {
+    Id(i64),
+    Username(String),
+}
+
+impl ChatId {
+    /// Whether a chat object from the Bot API is this chat
+    fn matches(&self, chat: &Value) -> bool {
+        match self {
+            ChatId::Id(id) => chat["id"].as_i64() == Some(*id),
+            ChatId::Username(name) => chat["username"].as_str()
+                .is_some_and(|username| name.trim_start_matches('@') == username),
+        }
+    }
+}
+
+impl TelegramConfig {
+    /// Whether there is a bot and a chat to talk to
+    pub fn is_configured(&self) -> bool {
+        !self.token.is_empty() && self.chat_id.is_some()
+    }
+
+    /// The state directory, created private to the current user. Answers
+    /// read from it approve tool calls, so one anybody else controls is refused.
+    fn state_dir(&self) -> Result<PathBuf, String> {
+        let dir = match self.state_dir {
+            Some(ref dir) => PathBuf::from(dir),
+            None => private_dir::default_dir("telegram"),
+        };
+        private_dir::create(&dir).map_err(|e| format!("state_dir: {}", e))?;
+        Ok(dir)
+    }
+
+    /// Whether the user behind a button click may answer for the chat
+    fn may_answer(&self, from: &Value, chat: &Value) -> bool {
+        let Some(user_id) = from["id"].as_i64() else { return false };
+        if !self.allowed_users.is_empty() {
+            return self.allowed_users.contains(&user_id);
+        }
+        chat["type"] == "private" && chat["id"].as_i64() == Some(user_id)
+    }
+
+    /// Keep the bot token out of error messages, which end up in logs
+    fn redact(&self, message: String) -> String {
+        if self.token.is_empty() {
+            message
+        } else {
+            message.replace(&self.token, "<token>")
+        }
+    }
+
+    fn request(&self, method: &str, params: &Value) -> Request {
+        // The token ends up in the URL, which curl reads from its config on stdin
+        let url = format!("{}/bot{}/{}", self.api_base.trim_end_matches('/'), self.token, method);
+        Request::new("POST", &url).timeout(self.retry.timeout()).json(params)
+    }
+
+    /// Call a Bot API method once and return its `result`
+    fn call(&self, method: &str, params: &Value, timeout: Duration) -> Result<Value, String> {
+        let response = self.request(method, params).timeout(timeout).send().map_err(|e| self.redact(e))?;
+        api_result(method, &response.body)
+    }
+}
+
+pub struct TelegramBackend {
+    config: TelegramConfig,
+}
+
+impl TelegramBackend {
+    pub fn new(config: TelegramConfig) -> Self {
+        TelegramBackend { config }
+    }
+
+    fn check_configured(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.config.is_configured() {
+            Ok(())
+        } else {
+            Err("backends.telegram.token and chat_id must be set".into())
+        }
+    }
+}
+
+impl NotificationBackend for TelegramBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        self.check_configured()?;
+        if !self.config.events.iter().any(|pattern| glob_match(pattern, ctx.event)) {
+            return Ok(());
+        }
+
+        let params = json!({
+            "chat_id": self.config.chat_id,
+            "text": format_html(&ChatMessage::new(data, ctx, &ChatConfig::default())),
+            "parse_mode": "HTML",
+        });
+        let response = webhook::send_with_retries(&self.config.request("sendMessage", &params), &self.config.retry.for_context(ctx))
+            .map_err(|failure| self.config.redact(failure.message))?;
+        api_result("sendMessage", &response.body)?;
+        Ok(())
+    }
+
+    /// Send the message with a button per choice and wait for a click. The
+    /// daemon long-polls for clicks; without one, this process polls itself.
+    fn ask(
+        &self,
+        data: &NotificationData,
+        choices: &[&str],
+        timeout: Duration,
+        ctx: &DeliveryContext,
+    ) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+        self.check_configured()?;
+
+        let text = format_html(&ChatMessage::new(data, ctx, &ChatConfig::default()));
+        let buttons: Vec<Value> = choices.iter().enumerate()
+            .map(|(index, label)| json!({ "text": label, "callback_data": index.to_string() }))
+            .collect();
+        let params = json!({
+            "chat_id": self.config.chat_id,
+            "text": text,
+            "parse_mode": "HTML",
+            "reply_markup": { "inline_keyboard": [buttons] },
+        });
+        let message = self.config.call("sendMessage", &params, self.config.retry.timeout())?;
+        let message_id = message["message_id"].as_i64().ok_or("sendMessage returned no message_id")?;
+
+        let state_dir = self.config.state_dir()?;
+        let approvals = Approvals::new(state_dir.clone());
+        approvals.expect(message_id)?;
+
+        let daemon_running = daemon::is_running(&ctx.config.daemon.socket_path());
+        let deadline = Instant::now() + timeout;
+        let choice = loop {
+            if let Some(choice) = approvals.take(message_id) {
+                break Some(choice);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break None;
+            }
+            // Only one process may poll at a time; whoever does hands every
+            // waiting process its answer
+            let lock = match daemon_running {
+                true => None,
+                false => FileLock::try_acquire(&state_dir.join("poll.lock"))?,
+            };
+            match lock {
+                Some(_lock) => {
+                    if let Err(e) = poll_once(&self.config, remaining.min(Duration::from_secs(10))) {
+                        approvals.forget(message_id);
+                        return Err(e.into());
+                    }
+                }
+                None => thread::sleep(remaining.min(Duration::from_millis(250))),
+            }
+        };
+        approvals.forget(message_id);
+
+        // Replace the buttons with the outcome so the chat shows what happened
+        let outcome = match choice.and_then(|choice| choices.get(choice)) {
+            Some(label) => format!("✅ {}", escape_html(label)),
+            None => "⌛ No answer".to_string(),
+        };
+        let edit = json!({
+            "chat_id": self.config.chat_id,
+            "message_id": message_id,
+            "text": format!("{}\n\n<b>{}</b>", text, outcome),
+            "parse_mode": "HTML",
+        });
+        self.config.call("editMessageText", &edit, self.config.retry.timeout()).ok();
+
+        Ok(choice)
+    }
+}
+
+/// Approvals waiting for a button click, as files shared between the process
+/// asking and the one polling for updates
+struct Approvals {
+    dir: PathBuf,
+}
+
+impl Approvals {
+    fn new(dir: PathBuf) -> Self {
+        Approvals { dir }
+    }
+
+    fn pending(&self, message_id: i64) -> PathBuf {
+        self.dir.join(format!("{}.pending", message_id))
+    }
+
+    fn answer(&self, message_id: i64) -> PathBuf {
+        self.dir.join(format!("{}.answer", message_id))
+    }
+
+    fn expect(&self, message_id: i64) -> std::io::Result<()> {
+        private_dir::write(&self.pending(message_id), b"")
+    }
+
+    /// Record a click, if someone is still waiting for it
+    fn resolve(&self, message_id: i64, choice: usize) -> bool {
+        if private_dir::read(&self.pending(message_id)).is_err() {
+            return false;
+        }
+        private_dir::write(&self.answer(message_id), choice.to_string().as_bytes()).is_ok()
+    }
+
+    fn take(&self, message_id: i64) -> Option<usize> {
+        let contents = private_dir::read(&self.answer(message_id)).ok()?;
+        fs::remove_file(self.answer(message_id)).ok();
+        String::from_utf8_lossy(&contents).trim().parse().ok()
+    }
+
+    fn forget(&self, message_id: i64) {
+        fs::remove_file(self.pending(message_id)).ok();
+        fs::remove_file(self.answer(message_id)).ok();
+    }
+}
+
+/// Fetch updates once, waiting up to `timeout` for one to arrive, and hand
+/// button clicks to whoever is waiting for them
+pub fn poll_once(config: &TelegramConfig, timeout: Duration) -> Result<(), String> {
+    let state_dir = config.state_dir()?;
+    let offset_path = state_dir.join("offset");
+    let offset: i64 = private_dir::read(&offset_path).ok()
+        .and_then(|offset| String::from_utf8_lossy(&offset).trim().parse().ok())
+        .unwrap_or(0);
+
+    let params = json!({
+        "offset": offset,
+        "timeout": timeout.as_secs(),
+        "allowed_updates": ["callback_query"],
+    });
+    let response = config.request("getUpdates", &params)
+        .timeout(timeout + Duration::from_secs(10))
+        .send()
+        .map_err(|e| config.redact(e))?;
+    // Another process or machine is polling with the same bot, and will
+    // hand out the answers it receives
+    if response.status == 409 {
+        thread::sleep(timeout.min(Duration::from_secs(1)));
+        return Ok(());
+    }
+    let updates = api_result("getUpdates", &response.body)?;
+    let approvals = Approvals::new(state_dir);
+
+    let mut next_offset = offset;
+    for update in updates.as_array().into_iter().flatten() {
+        next_offset = next_offset.max(update["update_id"].as_i64().unwrap_or(0) + 1);
+
+        let callback = &update["callback_query"];
+        let message = &callback["message"];
+        // Only the configured chat gets to decide, and within it only the
+        // users allowed to
+        if callback.is_null()
+            || !config.chat_id.as_ref().is_some_and(|chat| chat.matches(&message["chat"]))
+            || !config.may_answer(&callback["from"], &message["chat"])
+        {
+            continue;
+        }
+
+        let resolved = match (message["message_id"].as_i64(), callback["data"].as_str().and_then(|d| d.parse().ok())) {
+            (Some(message_id), Some(choice)) => approvals.resolve(message_id, choice),
+            _ => false,
+        };
+        let reply = json!({
+            "callback_query_id": callback["id"],
+            "text": if resolved { "Sent to Claude" } else { "This request has expired" },
+        });
+        config.call("answerCallbackQuery", &reply, config.retry.timeout()).ok();
+    }
+
+    // Confirm the updates so the next poll doesn't see them again
+    if next_offset != offset {
+        private_dir::write(&offset_path, next_offset.to_string().as_bytes()).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Long-poll for button clicks until the process exits, for the daemon
+pub fn poll_forever(config: TelegramConfig, on_error: impl Fn(String)) {
+    loop {
+        let result = config.state_dir()
+            .and_then(|dir| FileLock::acquire(&dir.join("poll.lock")).map_err(|e| e.to_string()))
+            .and_then(|_lock| poll_once(&config, Duration::from_secs(30)));
+        if let Err(e) = result {
+            on_error(e);
+            thread::sleep(Duration::from_secs(5));
+        }
+    }
+}
+
+fn api_result(method: &str, body: &str) -> Result<Value, String> {
+    let mut response: Value = serde_json::from_str(body)
+        .map_err(|e| format!("{}: invalid response: {}", method, e))?;
+    if response["ok"].as_bool() == Some(true) {
+        Ok(response["result"].take())
+    } else {
+        Err(format!("{}: {}", method, response["description"].as_str().unwrap_or("request failed")))
+    }
+}
+
+/// The message text, in Telegram's HTML subset
+fn format_html(message: &ChatMessage) -> String {
+    let mut text = format!("<b>{}</b>\n{}", escape_html(&message.title), escape_html(&message.body));
+    let fields = message.fields();
+    if !fields.is_empty() {
+        text.push('\n');
+    }
+    for (label, value) in fields {
+        text.push_str(&format!("\n<i>{}:</i> <code>{}</code>", label, escape_html(value)));
+    }
+    text
+}
+
+fn default_api_base() -> String { "https://api.telegram.org".to_string() }
+fn default_events() -> Vec<String> { vec!["*".to_string()] }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{fixtures, stub};
+    use crate::types::Config;
+
+    fn telegram(url: &str, name: &str) -> TelegramConfig {
+        let dir = std::env::temp_dir().join(format!("claude-notifier-telegram-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        let state_dir = format!("state_dir = \"{}\"", dir.display());
+        fixtures::config(url, &[r#"token = "123:abc""#, "chat_id = 42", r#"api_base = "{url}""#, &state_dir].join("\n"))
+    }
+
+    #[test]
+    fn test_send_formatted_message() {
+        let (url, received) = stub::serve(vec![(200, r#"{"ok":true,"result":{"message_id":1}}"#.to_string())]);
+        let app = Config::default();
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Stop", payload: None, inline: false };
+        TelegramBackend::new(telegram(&url, "send")).send(&fixtures::approval("Bash: ls <dir>"), &ctx).unwrap();
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.path, "/bot123:abc/sendMessage");
+        let body: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["chat_id"], 42);
+        assert_eq!(body["parse_mode"], "HTML");
+        assert_eq!(body["text"], "<b>Claude Needs Approval</b>\nBash: ls &lt;dir&gt;");
+    }
+
+    #[test]
+    fn test_ask_answered_by_inline_button() {
+        let callback = json!({
+            "ok": true,
+            "result": [
+                // Someone else's chat, and users of the group who aren't allowed, are ignored
+                { "update_id": 8, "callback_query": { "id": "cb0", "data": "0", "from": { "id": 5 }, "message": { "message_id": 7, "chat": { "id": 666, "type": "group" } } } },
+                { "update_id": 9, "callback_query": { "id": "cb1", "data": "0", "from": { "id": 6 }, "message": { "message_id": 7, "chat": { "id": 42, "type": "group" } } } },
+                { "update_id": 10, "callback_query": { "id": "cb2", "data": "1", "from": { "id": 5 }, "message": { "message_id": 7, "chat": { "id": 42, "type": "group" } } } }
+            ]
+        });
+        let (url, received) = stub::serve(vec![
+            (200, r#"{"ok":true,"result":{"message_id":7}}"#.to_string()),
+            (200, callback.to_string()),
+            (200, r#"{"ok":true,"result":true}"#.to_string()),
+            (200, r#"{"ok":true,"result":{"message_id":7}}"#.to_string()),
+        ]);
+
+        let mut config = telegram(&url, "ask");
+        config.allowed_users = vec![5];
+        let mut app = Config::default();
+        app.daemon.socket = Some("/nonexistent/claude-notifier.sock".to_string());
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "PreToolUse", payload: None, inline: false };
+        let choice = TelegramBackend::new(config.clone())
+            .ask(&fixtures::approval("Bash: ls <dir>"), &["Allow", "Deny"], Duration::from_secs(5), &ctx)
+            .unwrap();
+        assert_eq!(choice, Some(1));
+
+        let requests: Vec<_> = received.iter().take(4).collect();
+        let send: Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(send["reply_markup"]["inline_keyboard"][0][1], json!({ "text": "Deny", "callback_data": "1" }));
+        assert_eq!(requests[1].path, "/bot123:abc/getUpdates");
+        assert_eq!(requests[2].path, "/bot123:abc/answerCallbackQuery");
+        assert!(requests[2].body.contains("cb2"));
+        assert_eq!(requests[3].path, "/bot123:abc/editMessageText");
+        assert!(requests[3].body.contains("✅ Deny"));
+
+        let state_dir = config.state_dir().unwrap();
+        assert_eq!(fs::read_to_string(state_dir.join("offset")).unwrap(), "11");
+        fs::remove_dir_all(state_dir).ok();
+    }
+
+    #[test]
+    fn test_concurrent_polling_and_failures_keep_the_token_quiet() {
+        let (url, _received) = stub::serve(vec![
+            (409, r#"{"ok":false,"error_code":409,"description":"Conflict: terminated by other getUpdates request"}"#.to_string()),
+            (400, r#"{"ok":false,"description":"Bad Request"}"#.to_string()),
+        ]);
+        let config = telegram(&url, "conflict");
+        assert!(poll_once(&config, Duration::from_secs(0)).is_ok());
+
+        let app = Config::default();
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Stop", payload: None, inline: false };
+        let error = TelegramBackend::new(config.clone()).send(&fixtures::approval("Bash: ls <dir>"), &ctx).unwrap_err().to_string();
+        assert!(error.contains("/bot<token>/sendMessage") && !error.contains("123:abc"), "{}", error);
+        fs::remove_dir_all(config.state_dir().unwrap()).ok();
+    }
+}
@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use super::{DeliveryContext, NotificationBackend};
+use crate::terminal_notifier;
+use crate::types::NotificationData;
+
+pub const NAME: &str = "terminal-notifier";
+
+/// macOS notifications through the terminal-notifier binary
+pub struct TerminalNotifierBackend;
+
+impl NotificationBackend for TerminalNotifierBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        terminal_notifier::send_notification(data, ctx.config, ctx.session_id.map(String::from))
+    }
+
+    fn ask(
+        &self,
+        data: &NotificationData,
+        choices: &[&str],
+        timeout: Duration,
+        _ctx: &DeliveryContext,
+    ) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+        terminal_notifier::ask(data, choices, timeout)
+    }
+}
@@ -0,0 +1,535 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{DeliveryContext, NotificationBackend};
+use crate::debounce::now_ms;
+use crate::http::{Request, Response};
+use crate::private_dir;
+use crate::session_store::{SessionInfo, SessionStore};
+use crate::template::{Template, TemplateContext};
+use crate::types::{HookEvent, HookPayload, NotificationData};
+
+pub const NAME: &str = "webhook";
+
+/// Body strings replaced by raw JSON instead of being rendered as templates
+const RAW_PAYLOAD: &str = "{payload}";
+const RAW_SESSION: &str = "{session}";
+
+/// Spooled deliveries older than this are dropped instead of replayed
+const SPOOL_MAX_AGE_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// A claimed entry still unsent after this was claimed by a process that died
+const SPOOL_CLAIM_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+
+/// Limits for deliveries made inside the hook, so an endpoint that's down
+/// holds Claude up for seconds rather than most of a minute
+const INLINE_TIMEOUT_SECS: u64 = 5;
+const INLINE_RETRIES: u32 = 1;
+const INLINE_BACKOFF_MS: u64 = 500;
+
+/// A JSON request to any URL, configured under `[backends.webhook]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookConfig {
+    #[serde(default)]
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// JSON sent as the body. String values are templates; a string that is
+    /// exactly `{payload}` or `{session}` becomes the raw hook payload or
+    /// stored session.
+    #[serde(default = "default_body")]
+    pub body: Value,
+    #[serde(flatten)]
+    pub retry: RetryConfig,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            method: default_method(),
+            headers: BTreeMap::new(),
+            body: default_body(),
+            retry: RetryConfig::default(),
+        }
+    }
+}
+
+/// How hard to try an HTTP delivery before giving up on it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetryConfig {
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Attempts after the first one
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each one after it
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+    /// Keep deliveries that still fail on disk and retry them after the next
+    /// one that succeeds
+    #[serde(default = "default_spool")]
+    pub spool: bool,
+    /// Defaults to a directory only the current user can read, under
+    /// `$XDG_RUNTIME_DIR` or the system temp dir. Spooled requests keep their
+    /// credentials, so it must not be shared.
+    #[serde(default)]
+    pub spool_dir: Option<String>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_timeout_secs(),
+            retries: default_retries(),
+            backoff_ms: default_backoff_ms(),
+            spool: default_spool(),
+            spool_dir: None,
+        }
+    }
+}
+
+impl WebhookConfig {
+    /// Check every template in the body
+    pub fn validate(&self) -> Result<(), String> {
+        validate_body(&self.body)
+    }
+}
+
+impl RetryConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    /// The policy for a delivery made in `ctx`, capped when the hook delivers
+    /// inline
+    pub fn for_context(&self, ctx: &DeliveryContext) -> Cow<'_, RetryConfig> {
+        if !ctx.inline {
+            return Cow::Borrowed(self);
+        }
+        Cow::Owned(RetryConfig {
+            timeout_secs: self.timeout_secs.min(INLINE_TIMEOUT_SECS),
+            retries: self.retries.min(INLINE_RETRIES),
+            backoff_ms: self.backoff_ms.min(INLINE_BACKOFF_MS),
+            ..self.clone()
+        })
+    }
+
+    /// The spool for `backend`, if spooling is enabled
+    pub fn spool_for(&self, backend: &str) -> Option<Spool> {
+        if !self.spool {
+            return None;
+        }
+        let base_dir = match self.spool_dir {
+            Some(ref dir) => PathBuf::from(dir),
+            None => private_dir::default_dir("spool"),
+        };
+        Some(Spool::with_base_dir(base_dir.join(backend)))
+    }
+}
+
+pub struct WebhookBackend {
+    config: WebhookConfig,
+}
+
+impl WebhookBackend {
+    pub fn new(config: WebhookConfig) -> Self {
+        WebhookBackend { config }
+    }
+}
+
+impl NotificationBackend for WebhookBackend {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn send(&self, data: &NotificationData, ctx: &DeliveryContext) -> Result<(), Box<dyn std::error::Error>> {
+        if self.config.url.is_empty() {
+            return Err("backends.webhook.url is not set".into());
+        }
+
+        let payload = payload_or_stub(ctx);
+        let session = ctx.session_id.and_then(|id| SessionStore::open().get_session(id));
+        let body = render_body(&self.config.body, data, &payload, session.as_ref());
+
+        let mut request = Request::new(&self.config.method, &self.config.url)
+            .timeout(self.config.retry.timeout())
+            .json(&body);
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+
+        deliver(&request, &self.config.retry, NAME, ctx)?;
+        Ok(())
+    }
+}
+
+/// The hook payload, or a stand-in naming the event and session for
+/// notifications that don't come straight from a hook
+pub fn payload_or_stub<'a>(ctx: &DeliveryContext<'a>) -> Cow<'a, HookPayload> {
+    match ctx.payload {
+        Some(payload) => Cow::Borrowed(payload),
+        None => Cow::Owned(HookPayload {
+            session_id: ctx.session_id.map(String::from),
+            transcript_path: None,
+            cwd: None,
+            event: HookEvent::Unknown(json!({ "hook_event_name": ctx.event })),
+        }),
+    }
+}
+
+/// Render every template in `body`
+pub fn render_body(body: &Value, data: &NotificationData, payload: &HookPayload, session: Option<&SessionInfo>) -> Value {
+    let ctx = TemplateContext {
+        payload,
+        terminal: session.map(|s| &s.terminal_info),
+        title: &data.title,
+        body: &data.body,
+    };
+    render_value(body, &ctx, session)
+}
+
+fn render_value(value: &Value, ctx: &TemplateContext, session: Option<&SessionInfo>) -> Value {
+    match value {
+        Value::String(source) if source == RAW_PAYLOAD => serde_json::to_value(ctx.payload).unwrap_or_default(),
+        Value::String(source) if source == RAW_SESSION => serde_json::to_value(session).unwrap_or_default(),
+        Value::String(source) => match Template::parse(source) {
+            Ok(template) => Value::String(template.render(|path| ctx.resolve(path))),
+            Err(_) => value.clone(),
+        },
+        Value::Array(items) => Value::Array(items.iter().map(|item| render_value(item, ctx, session)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields.iter().map(|(key, item)| (key.clone(), render_value(item, ctx, session))).collect()
+        ),
+        other => other.clone(),
+    }
+}
+
+fn validate_body(body: &Value) -> Result<(), String> {
+    match body {
+        Value::String(source) if source == RAW_PAYLOAD || source == RAW_SESSION => Ok(()),
+        Value::String(source) => Template::parse(source).and_then(|template| template.validate_fields()),
+        Value::Array(items) => items.iter().try_for_each(validate_body),
+        Value::Object(fields) => fields.values().try_for_each(validate_body),
+        _ => Ok(()),
+    }
+}
+
+/// Why a request didn't get through
+#[derive(Debug)]
+pub struct Failure {
+    pub message: String,
+    /// Whether trying again later might work
+    pub retryable: bool,
+}
+
+/// Send `request`, retrying transport errors and retryable statuses with
+/// exponential backoff. No attempt takes longer than `retry` allows.
+pub fn send_with_retries(request: &Request, retry: &RetryConfig) -> Result<Response, Failure> {
+    let request = &Request { timeout: request.timeout.min(retry.timeout()), ..request.clone() };
+    let mut attempt = 0;
+    loop {
+        let failure = match request.send() {
+            Ok(response) if response.is_success() => return Ok(response),
+            Ok(response) => Failure {
+                message: format!("{} answered {}: {}", request.url, response.status, response.body.trim()),
+                retryable: response.is_retryable(),
+            },
+            Err(message) => Failure { message, retryable: true },
+        };
+
+        if !failure.retryable || attempt >= retry.retries {
+            return Err(failure);
+        }
+        thread::sleep(Duration::from_millis(retry.backoff_ms.saturating_mul(1 << attempt.min(16))));
+        attempt += 1;
+    }
+}
+
+/// Send `request` for `backend` with retries. A delivery that still fails is
+/// spooled unless it's secret, and a successful one replays whatever was spooled before it,
+/// unless the hook is delivering inline and can't wait for that.
+pub fn deliver(request: &Request, retry: &RetryConfig, backend: &str, ctx: &DeliveryContext) -> Result<Response, String> {
+    let retry = retry.for_context(ctx);
+    let spool = retry.spool_for(backend);
+
+    match send_with_retries(request, &retry) {
+        Ok(response) => {
+            if let (Some(spool), false) = (spool, ctx.inline) {
+                spool.replay(now_ms());
+            }
+            Ok(response)
+        }
+        Err(failure) if failure.retryable => match spool {
+            Some(_) if request.secret => Err(format!("{} (not spooled, it carries a token)", failure.message)),
+            Some(spool) => match spool.save(request, now_ms()) {
+                Ok(()) => Err(format!("{} (spooled for later)", failure.message)),
+                Err(e) => Err(format!("{} (spooling failed: {})", failure.message, e)),
+            },
+            None => Err(failure.message),
+        },
+        Err(failure) => Err(failure.message),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SpooledRequest {
+    at_ms: u64,
+    request: Request,
+}
+
+/// Failed requests waiting on disk for the endpoint to come back. Entries
+/// hold credentials, so the directory and its files are the user's alone, and
+/// nothing in it is replayed unless the user owns it.
+pub struct Spool {
+    base_dir: PathBuf,
+}
+
+impl Spool {
+    pub fn with_base_dir(base_dir: PathBuf) -> Self {
+        Spool { base_dir }
+    }
+
+    pub fn save(&self, request: &Request, now_ms: u64) -> std::io::Result<()> {
+        private_dir::create(&self.base_dir)?;
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+        let name = format!("{}-{}.json", nanos, std::process::id());
+        let entry = SpooledRequest { at_ms: now_ms, request: request.clone() };
+        private_dir::write(&self.base_dir.join(name), &serde_json::to_vec(&entry)?)
+    }
+
+    /// Paths of the spooled requests, oldest first. Empty if the directory
+    /// isn't private to the current user.
+    pub fn entries(&self) -> Vec<PathBuf> {
+        self.paths_with_extension("json")
+    }
+
+    fn paths_with_extension(&self, extension: &str) -> Vec<PathBuf> {
+        if !self.base_dir.exists() || private_dir::create(&self.base_dir).is_err() {
+            return Vec::new();
+        }
+        let Ok(dir) = fs::read_dir(&self.base_dir) else { return Vec::new() };
+        let mut paths: Vec<PathBuf> = dir
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension().is_some_and(|ext| ext == extension)
+                    && !path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            })
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// Send spooled requests once each, oldest first, stopping at the first
+    /// one that fails in a way worth retrying
+    pub fn replay(&self, now_ms: u64) {
+        self.recover_stale_claims(now_ms);
+
+        for path in self.entries() {
+            // Claim the entry so a concurrent replay doesn't send it twice
+            let claimed = path.with_extension(format!("json.{}.sending", now_ms));
+            if fs::rename(&path, &claimed).is_err() {
+                continue;
+            }
+
+            let entry: Option<SpooledRequest> = private_dir::read(&claimed).ok()
+                .and_then(|contents| serde_json::from_slice(&contents).ok());
+            let Some(entry) = entry.filter(|e| now_ms.saturating_sub(e.at_ms) < SPOOL_MAX_AGE_MS) else {
+                fs::remove_file(&claimed).ok();
+                continue;
+            };
+
+            let retryable = match entry.request.send() {
+                Ok(response) => !response.is_success() && response.is_retryable(),
+                Err(_) => true,
+            };
+            if retryable {
+                fs::rename(&claimed, &path).ok();
+                return;
+            }
+            fs::remove_file(&claimed).ok();
+        }
+    }
+
+    /// Put back entries claimed by a replay that never finished, e.g. because
+    /// its process was killed
+    fn recover_stale_claims(&self, now_ms: u64) {
+        for claimed in self.paths_with_extension("sending") {
+            // `<entry>.json.<claimed at>.sending`
+            let Some(stem) = claimed.file_stem().map(|stem| stem.to_string_lossy().to_string()) else { continue };
+            let Some((entry, claimed_at)) = stem.rsplit_once('.') else { continue };
+            let Ok(claimed_at) = claimed_at.parse::<u64>() else { continue };
+            if now_ms.saturating_sub(claimed_at) > SPOOL_CLAIM_TIMEOUT_MS {
+                fs::rename(&claimed, self.base_dir.join(entry)).ok();
+            }
+        }
+    }
+}
+
+fn default_method() -> String { "POST".to_string() }
+fn default_timeout_secs() -> u64 { 10 }
+fn default_retries() -> u32 { 3 }
+fn default_backoff_ms() -> u64 { 500 }
+fn default_spool() -> bool { true }
+
+fn default_body() -> Value {
+    json!({
+        "event": "{event}",
+        "title": "{title}",
+        "body": "{body}",
+        "session_id": "{session_id}",
+        "cwd": "{cwd}",
+        "timestamp": "{timestamp}",
+        "payload": RAW_PAYLOAD,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{fixtures, stub};
+    use crate::types::Config;
+
+    fn spool_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("claude-notifier-webhook-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    fn webhook(url: &str, spool: &std::path::Path, extra: &str) -> WebhookConfig {
+        let spool_dir = format!("spool_dir = \"{}\"", spool.display());
+        fixtures::config(url, &[r#"url = "{url}/hook""#, "backoff_ms = 1", &spool_dir, extra].join("\n"))
+    }
+
+    fn send(config: WebhookConfig, inline: bool) -> Result<(), String> {
+        let app = Config::default();
+        let payload = fixtures::payload(json!({
+            "hook_event_name": "Notification",
+            "session_id": "s1",
+            "message": "Claude needs your permission to use Bash"
+        }));
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Notification", payload: Some(&payload), inline };
+        WebhookBackend::new(config).send(&fixtures::approval("Bash"), &ctx).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_templated_body_and_headers() {
+        let (url, received) = stub::serve(vec![(204, String::new())]);
+        let dir = spool_dir("body");
+        let config = webhook(&url, &dir, r#"
+            headers = { Authorization = "Bearer secret" }
+            [body]
+            text = "{title}: {message | upper}"
+            raw = "{payload}"
+            tags = ["claude", "{event}"]
+            priority = 5
+        "#);
+        assert!(config.validate().is_ok());
+
+        send(config, false).unwrap();
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/hook");
+        assert_eq!(request.header("Authorization"), Some("Bearer secret"));
+        assert_eq!(request.header("Content-Type"), Some("application/json"));
+        let body: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["text"], "Claude Needs Approval: CLAUDE NEEDS YOUR PERMISSION TO USE BASH");
+        assert_eq!(body["raw"]["session_id"], "s1");
+        assert_eq!(body["tags"], json!(["claude", "Notification"]));
+        assert_eq!(body["priority"], 5);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_retries_then_gives_up_on_client_errors() {
+        let (url, received) = stub::serve(vec![(500, String::new()), (429, String::new()), (200, String::new())]);
+        let dir = spool_dir("retry");
+        send(webhook(&url, &dir, ""), false).unwrap();
+        assert_eq!(received.iter().take(3).count(), 3);
+
+        let (url, received) = stub::serve(vec![(400, "bad".to_string())]);
+        let error = send(webhook(&url, &dir, ""), false).unwrap_err();
+        assert!(error.contains("400: bad"), "{}", error);
+        assert_eq!(received.iter().count(), 1);
+        assert!(Spool::with_base_dir(dir.join(NAME)).entries().is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_failed_delivery_spooled_and_replayed() {
+        let dir = spool_dir("spool");
+
+        let (url, _received) = stub::serve(vec![(503, String::new())]);
+        let error = send(webhook(&url, &dir, "retries = 0"), false).unwrap_err();
+        assert!(error.contains("spooled"), "{}", error);
+        assert_eq!(Spool::with_base_dir(dir.join(NAME)).entries().len(), 1);
+
+        // The spooled request still points at the first stub, so replay it
+        // against the second by rewriting its URL
+        let (url, received) = stub::serve(vec![(200, String::new()), (200, String::new())]);
+        let path = &Spool::with_base_dir(dir.join(NAME)).entries()[0];
+        let mut entry: SpooledRequest = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+        entry.request.url = format!("{}/replayed", url);
+        fs::write(path, serde_json::to_vec(&entry).unwrap()).unwrap();
+
+        // The hook delivering inline leaves the spool to a later delivery
+        let (inline_url, inline_received) = stub::serve(vec![(200, String::new())]);
+        send(webhook(&inline_url, &dir, ""), true).unwrap();
+        assert_eq!(inline_received.iter().count(), 1);
+        assert_eq!(Spool::with_base_dir(dir.join(NAME)).entries().len(), 1);
+
+        send(webhook(&url, &dir, ""), false).unwrap();
+        let paths: Vec<String> = received.iter().take(2).map(|r| r.path).collect();
+        assert_eq!(paths, vec!["/hook", "/replayed"]);
+        assert!(Spool::with_base_dir(dir.join(NAME)).entries().is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_secret_requests_are_not_spooled() {
+        let dir = spool_dir("secret");
+        let (url, _received) = stub::serve(vec![(503, String::new())]);
+        let config = webhook(&url, &dir, "retries = 0");
+        let app = Config::default();
+        let ctx = DeliveryContext { config: &app, session_id: None, event: "Stop", payload: None, inline: false };
+
+        let request = Request::new("POST", &config.url).header("Authorization", "Bearer secret").secret();
+        let error = deliver(&request, &config.retry, NAME, &ctx).unwrap_err();
+        assert!(error.contains("not spooled"), "{}", error);
+        assert!(Spool::with_base_dir(dir.join(NAME)).entries().is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_spool_is_private_and_recovers_claims() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = spool_dir("private");
+        let spool = Spool::with_base_dir(dir.clone());
+        let request = Request::new("POST", "http://127.0.0.1:9/hook").header("Authorization", "Bearer secret");
+        spool.save(&request, 0).unwrap();
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        let path = spool.entries().remove(0);
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        // Left claimed by a replay that died
+        let claimed = path.with_extension("json.1000.sending");
+        fs::rename(&path, &claimed).unwrap();
+        spool.recover_stale_claims(1000 + SPOOL_CLAIM_TIMEOUT_MS / 2);
+        assert!(spool.entries().is_empty());
+        spool.recover_stale_claims(1000 + SPOOL_CLAIM_TIMEOUT_MS * 2);
+        assert_eq!(spool.entries(), vec![path]);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
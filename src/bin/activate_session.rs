@@ -1,10 +1,31 @@
+use claude_notifier::backends::dbus;
 use claude_notifier::session_store::SessionStore;
 use claude_notifier::terminal_detector;
 use std::env;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    
+
+    // Spawned by the D-Bus backend with its bus connection as stdin: wait for
+    // the notification to be clicked first
+    if args.len() == 4 && args[1] == "--dbus-notification" {
+        let notification_id: u32 = match args[2].parse() {
+            Ok(id) => id,
+            Err(_) => {
+                eprintln!("Invalid notification id: {}", args[2]);
+                std::process::exit(1);
+            }
+        };
+
+        match dbus::watch_inherited(notification_id, dbus::WATCH_TIMEOUT) {
+            Some(action) if action == "default" || action == dbus::ACTIVATE_ACTION => {}
+            _ => return,
+        }
+
+        activate(&args[3]);
+        return;
+    }
+
     if args.len() != 2 {
         eprintln!("Usage: {} <session_id>", args[0]);
         eprintln!("\nThis tool activates the terminal window for a given Claude session.");
@@ -23,7 +44,10 @@ fn main() {
         std::process::exit(1);
     }
     
-    let session_id = &args[1];
+    activate(&args[1]);
+}
+
+fn activate(session_id: &str) {
     let store = SessionStore::new();
     
     match store.get_session(session_id) {
@@ -20,6 +20,7 @@ fn main() {
         println!("Project Dir: {:?}", info.project_dir);
         println!("Parent PID: {:?}", info.parent_pid);
         println!("CWD: {:?}", info.cwd);
+        println!("TTY: {:?}", info.tty);
         
         println!("\n=== Environment Variables ===");
         println!("TERM_PROGRAM: {:?}", env::var("TERM_PROGRAM").ok());
@@ -0,0 +1,240 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::debounce::now_ms;
+use crate::detach::Job;
+use crate::private_dir;
+use crate::terminal_detector::DetectionContext;
+use crate::types::HookPayload;
+
+/// Deliveries that can run at once; one slow backend only holds up the
+/// sessions that share its worker
+const WORKERS: usize = 4;
+
+/// The long-running daemon, configured under `[daemon]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DaemonConfig {
+    /// Socket path; `claude-notifier.sock` in `$XDG_RUNTIME_DIR` if unset
+    #[serde(default)]
+    pub socket: Option<String>,
+    /// How long the hook waits for the daemon before delivering itself
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            socket: None,
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+impl DaemonConfig {
+    /// Without `$XDG_RUNTIME_DIR`, the socket goes in a directory only the
+    /// user can enter, so nobody else can bind it first
+    pub fn socket_path(&self) -> PathBuf {
+        match self.socket {
+            Some(ref path) => PathBuf::from(path),
+            None => std::env::var_os("XDG_RUNTIME_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| private_dir::default_dir("daemon"))
+                .join("claude-notifier.sock"),
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+/// Whether a daemon is accepting connections on `path`
+pub fn is_running(path: &Path) -> bool {
+    connect(path).is_ok()
+}
+
+/// Connect to the socket at `path` if the current user created it. Payloads
+/// hold prompts and commands, so they only go to our own daemon.
+fn connect(path: &Path) -> io::Result<UnixStream> {
+    private_dir::owned(path)?;
+    UnixStream::connect(path)
+}
+
+/// Hand a job to the daemon and wait for it to be accepted
+pub fn submit(path: &Path, job: &Job, timeout: Duration) -> io::Result<()> {
+    let mut stream = connect(path)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let mut line = serde_json::to_string(job)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    match reply.trim_end() {
+        "ok" => Ok(()),
+        "" => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Daemon closed the connection")),
+        other => Err(io::Error::other(format!("Daemon rejected the payload: {}", other))),
+    }
+}
+
+/// Parse one line of the protocol: a job from our own client, or a bare hook
+/// payload from anything else
+pub fn parse_line(line: &str) -> Result<(Job, HookPayload), String> {
+    let job = serde_json::from_str::<Job>(line).unwrap_or_else(|_| Job {
+        payload: line.to_string(),
+        context: DetectionContext::default(),
+        decided: false,
+        received_at_ms: now_ms(),
+    });
+    let payload = serde_json::from_str(&job.payload).map_err(|e| e.to_string())?;
+    Ok((job, payload))
+}
+
+/// Bind the daemon socket, replacing a stale one left by a daemon that died
+pub fn bind(path: &Path) -> io::Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("A daemon is already listening on {}", path.display()),
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+
+    if let Some(parent) = path.parent().filter(|parent| !parent.exists()) {
+        private_dir::create(parent)?;
+    }
+
+    // Payloads include prompts and commands, so keep other users out. The
+    // socket is created with these permissions rather than changed after, so
+    // nobody can connect in between.
+    // SAFETY: umask has no preconditions; the daemon binds before it starts
+    // any threads that create files
+    let previous = unsafe { libc::umask(0o077) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(previous) };
+    listener
+}
+
+/// Accept connections forever, handing every payload to `handle`.
+///
+/// Each line is acknowledged with `ok` once it's queued, or `error: ...` if it
+/// isn't a valid payload. Deliveries run on a few worker threads, and every
+/// payload of a session goes to the same one, so a session's events are
+/// handled in the order they arrived.
+pub fn serve(listener: UnixListener, handle: impl Fn(Job, HookPayload) + Send + Sync + 'static) -> io::Result<()> {
+    let handle = Arc::new(handle);
+    let workers: Vec<Sender<(Job, HookPayload)>> = (0..WORKERS)
+        .map(|_| {
+            let (sender, receiver) = mpsc::channel::<(Job, HookPayload)>();
+            let handle = Arc::clone(&handle);
+            thread::spawn(move || {
+                for (job, payload) in receiver {
+                    handle(job, payload);
+                }
+            });
+            sender
+        })
+        .collect();
+    let workers = Arc::new(workers);
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let workers = Arc::clone(&workers);
+        thread::spawn(move || handle_connection(stream, &workers));
+    }
+
+    Ok(())
+}
+
+/// The worker that handles every payload of `session_id`
+fn worker_for(session_id: Option<&str>, workers: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    session_id.hash(&mut hasher);
+    (hasher.finish() % workers as u64) as usize
+}
+
+fn handle_connection(stream: UnixStream, workers: &[Sender<(Job, HookPayload)>]) {
+    let Ok(mut writer) = stream.try_clone() else { return };
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match parse_line(&line) {
+            Ok(item) => match workers[worker_for(item.1.session_id.as_deref(), workers.len())].send(item) {
+                Ok(()) => "ok".to_string(),
+                Err(_) => "error: daemon is shutting down".to_string(),
+            },
+            Err(e) => format!("error: {}", e),
+        };
+        if writeln!(writer, "{}", reply).is_err() {
+            break;
+        }
+    }
+}
+
+fn default_timeout_ms() -> u64 { 200 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::MetadataExt;
+    use std::sync::mpsc::Receiver;
+
+    fn start(name: &str) -> (PathBuf, Receiver<HookPayload>) {
+        let path = std::env::temp_dir().join(format!("claude-notifier-{}-{}.sock", name, std::process::id()));
+        let listener = bind(&path).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || serve(listener, move |_, payload| { sender.send(payload).ok(); }));
+        (path, receiver)
+    }
+
+    #[test]
+    fn test_submit_job() {
+        let (path, received) = start("submit");
+        let job = Job::new(r#"{"hook_event_name":"Stop","session_id":"abc"}"#.to_string());
+
+        submit(&path, &job, Duration::from_secs(5)).unwrap();
+        let payload = received.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(payload.event.name(), "Stop");
+        assert_eq!(payload.session_id.as_deref(), Some("abc"));
+
+        let bad = Job::new("not json".to_string());
+        assert!(submit(&path, &bad, Duration::from_secs(5)).is_err());
+        assert!(bind(&path).is_err());
+        assert_eq!(fs::metadata(&path).unwrap().mode() & 0o077, 0);
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_newline_delimited_bare_payloads() {
+        let (path, received) = start("bare");
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream.write_all(b"{\"hook_event_name\":\"Stop\"}\n\n{\"hook_event_name\":\"SessionEnd\",\"reason\":\"exit\"}\n").unwrap();
+
+        let mut replies = BufReader::new(&stream).lines();
+        assert_eq!(replies.next().unwrap().unwrap(), "ok");
+        assert_eq!(replies.next().unwrap().unwrap(), "ok");
+        assert_eq!(received.recv_timeout(Duration::from_secs(5)).unwrap().event.name(), "Stop");
+        assert_eq!(received.recv_timeout(Duration::from_secs(5)).unwrap().event.name(), "SessionEnd");
+        assert_eq!(worker_for(Some("abc"), WORKERS), worker_for(Some("abc"), WORKERS));
+        fs::remove_file(&path).ok();
+    }
+}
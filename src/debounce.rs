@@ -0,0 +1,247 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::session_store::{PendingSummary, SentNotification, SessionStore};
+use crate::types::{Config, NotificationData};
+
+/// Burst suppression, configured under `[notifications.debounce]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DebounceConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// A repeat within this many seconds of the previous one is held back
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    #[serde(default)]
+    pub scope: DebounceScope,
+    /// Send one summary of everything held back once the burst is over
+    #[serde(default = "default_summarize")]
+    pub summarize: bool,
+    /// Events that are never held back
+    #[serde(default = "default_exempt_events")]
+    pub exempt_events: Vec<String>,
+}
+
+impl Default for DebounceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: default_window_secs(),
+            scope: DebounceScope::default(),
+            summarize: default_summarize(),
+            exempt_events: default_exempt_events(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DebounceScope {
+    /// Only notifications with the same title and body count as repeats
+    Identical,
+    /// Any notification for the same event counts as a repeat
+    #[default]
+    Category,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DebounceDecision {
+    Send,
+    /// Held back; `starts_burst` is set when nothing else in the session was
+    /// waiting to be summarized, so a process needs to start sending summaries
+    Suppress { starts_burst: bool },
+}
+
+impl DebounceConfig {
+    fn window_ms(&self) -> u64 {
+        self.window_secs * 1000
+    }
+
+    fn key(&self, event: &str, data: &NotificationData) -> String {
+        match self.scope {
+            DebounceScope::Identical => format!("{}\n{}\n{}", event, data.title, data.body),
+            DebounceScope::Category => event.to_string(),
+        }
+    }
+}
+
+/// Record a notification for `session_id` and decide whether it should go out
+pub fn check(
+    store: &SessionStore,
+    session_id: &str,
+    event: &str,
+    tool_name: Option<&str>,
+    data: &NotificationData,
+    config: &DebounceConfig,
+    now_ms: u64,
+) -> DebounceDecision {
+    if !config.enabled || config.exempt_events.iter().any(|e| e == event) {
+        return DebounceDecision::Send;
+    }
+
+    let key = config.key(event, data);
+    let window_ms = config.window_ms();
+
+    store.update_session(session_id, |session| {
+        session.recent.retain(|sent| now_ms.saturating_sub(sent.sent_at_ms) < window_ms);
+
+        match session.recent.iter_mut().find(|sent| sent.key == key) {
+            Some(sent) => {
+                // Slide the window so a steady stream stays collapsed
+                sent.sent_at_ms = now_ms;
+
+                let starts_burst = session.pending_summaries.is_empty();
+                let index = match session.pending_summaries.iter().position(|p| p.key == key) {
+                    Some(index) => index,
+                    None => {
+                        session.pending_summaries.push(PendingSummary {
+                            key: key.clone(),
+                            event: event.to_string(),
+                            count: 0,
+                            tools: Vec::new(),
+                            last_at_ms: now_ms,
+                        });
+                        session.pending_summaries.len() - 1
+                    }
+                };
+                let pending = &mut session.pending_summaries[index];
+                pending.count += 1;
+                pending.last_at_ms = now_ms;
+                if let Some(tool) = tool_name {
+                    if !pending.tools.iter().any(|t| t == tool) {
+                        pending.tools.push(tool.to_string());
+                    }
+                }
+
+                DebounceDecision::Suppress { starts_burst }
+            }
+            None => {
+                session.recent.push(SentNotification { key, sent_at_ms: now_ms });
+                DebounceDecision::Send
+            }
+        }
+    }).unwrap_or(DebounceDecision::Send)
+}
+
+/// Take the pending summaries whose burst has been quiet for a full window,
+/// along with how long to wait for the next one if others are still going
+pub fn take_summaries(
+    store: &SessionStore,
+    session_id: &str,
+    config: &DebounceConfig,
+    now_ms: u64,
+) -> (Vec<PendingSummary>, Option<Duration>) {
+    let window_ms = config.window_ms();
+
+    store.update_session(session_id, |session| {
+        let (due, waiting): (Vec<_>, Vec<_>) = session.pending_summaries
+            .drain(..)
+            .partition(|pending| now_ms >= pending.last_at_ms + window_ms);
+        let wait = waiting.iter()
+            .map(|pending| Duration::from_millis(pending.last_at_ms + window_ms - now_ms))
+            .min();
+        session.pending_summaries = waiting;
+        (due, wait)
+    }).unwrap_or_default()
+}
+
+/// The notification sent in place of everything held back during a burst
+pub fn summary_notification(summary: &PendingSummary, config: &Config) -> NotificationData {
+    let count = summary.count;
+    let plural = if count == 1 { "" } else { "s" };
+
+    let body = match summary.event.as_str() {
+        "PreToolUse" | "PostToolUse" if summary.tools.is_empty() => {
+            format!("Claude ran {count} more tool{plural}")
+        }
+        "PreToolUse" | "PostToolUse" => {
+            format!("Claude ran {count} more tool{plural} ({})", summary.tools.join(", "))
+        }
+        event => format!("{count} more {event} notification{plural}"),
+    };
+
+    NotificationData {
+        title: "Claude Activity".to_string(),
+        body,
+        sound: config.notifications.sounds.tool_use.clone(),
+    }
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default()
+}
+
+fn default_window_secs() -> u64 { 10 }
+fn default_summarize() -> bool { true }
+fn default_exempt_events() -> Vec<String> { vec!["Notification".to_string()] }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::fixtures;
+
+    fn data(body: &str) -> NotificationData {
+        NotificationData { title: "Claude Tool Use".to_string(), body: body.to_string(), sound: "Pop".to_string() }
+    }
+
+    #[test]
+    fn test_burst_is_collapsed_into_summary() {
+        let (store, dir) = fixtures::store("burst");
+        let config = DebounceConfig { enabled: true, ..DebounceConfig::default() };
+
+        let check_at = |ms, tool| check(&store, "s1", "PreToolUse", Some(tool), &data(tool), &config, ms);
+        assert_eq!(check_at(0, "Read"), DebounceDecision::Send);
+        assert_eq!(check_at(2_000, "Grep"), DebounceDecision::Suppress { starts_burst: true });
+        assert_eq!(check_at(4_000, "Read"), DebounceDecision::Suppress { starts_burst: false });
+
+        // Still within the window of the last suppressed event
+        assert_eq!(take_summaries(&store, "s1", &config, 10_000), (Vec::new(), Some(Duration::from_secs(4))));
+
+        let (summaries, wait) = take_summaries(&store, "s1", &config, 14_000);
+        assert_eq!(wait, None);
+        assert_eq!(summaries[0].count, 2);
+        assert_eq!(summary_notification(&summaries[0], &Config::default()).body, "Claude ran 2 more tools (Grep, Read)");
+        assert_eq!(take_summaries(&store, "s1", &config, 15_000), (Vec::new(), None));
+
+        // The burst is over, so the next event goes out again
+        assert_eq!(check_at(30_000, "Edit"), DebounceDecision::Send);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_bursts_of_different_events_summarized_apart() {
+        let (store, dir) = fixtures::store("events");
+        let config = DebounceConfig { enabled: true, ..DebounceConfig::default() };
+
+        let check_at = |ms, event| check(&store, "s1", event, None, &data(event), &config, ms);
+        assert_eq!(check_at(0, "PreToolUse"), DebounceDecision::Send);
+        assert_eq!(check_at(1_000, "Stop"), DebounceDecision::Send);
+        assert_eq!(check_at(2_000, "PreToolUse"), DebounceDecision::Suppress { starts_burst: true });
+        // A summary is already on its way for the session
+        assert_eq!(check_at(5_000, "Stop"), DebounceDecision::Suppress { starts_burst: false });
+
+        let (summaries, wait) = take_summaries(&store, "s1", &config, 12_000);
+        assert_eq!(summaries.iter().map(|s| s.event.as_str()).collect::<Vec<_>>(), vec!["PreToolUse"]);
+        assert_eq!(wait, Some(Duration::from_secs(3)));
+        let (summaries, wait) = take_summaries(&store, "s1", &config, 15_000);
+        assert_eq!((summaries[0].event.as_str(), summaries[0].count, wait), ("Stop", 1, None));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_identical_scope_and_exempt_events() {
+        let (store, dir) = fixtures::store("identical");
+        let config = DebounceConfig { enabled: true, scope: DebounceScope::Identical, ..DebounceConfig::default() };
+
+        assert_eq!(check(&store, "s1", "PreToolUse", None, &data("a"), &config, 0), DebounceDecision::Send);
+        assert_eq!(check(&store, "s1", "PreToolUse", None, &data("b"), &config, 1), DebounceDecision::Send);
+        assert_eq!(
+            check(&store, "s1", "PreToolUse", None, &data("a"), &config, 2),
+            DebounceDecision::Suppress { starts_burst: true }
+        );
+        assert_eq!(check(&store, "s1", "Notification", None, &data("x"), &config, 3), DebounceDecision::Send);
+        assert_eq!(check(&store, "s1", "Notification", None, &data("x"), &config, 4), DebounceDecision::Send);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::debounce::now_ms;
+use crate::private_dir;
+use crate::terminal_detector::DetectionContext;
+
+/// A hook payload handed from the hook process to a detached delivery process
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Job {
+    /// The payload exactly as Claude Code sent it
+    pub payload: String,
+    /// The hook's parent and environment, so terminal detection still works
+    /// once the hook has exited
+    #[serde(default)]
+    pub context: DetectionContext,
+    /// The hook already answered this `PreToolUse` itself, so only the
+    /// session's bookkeeping is left to do
+    #[serde(default)]
+    pub decided: bool,
+    /// When the hook ran, which orders jobs that are delivered concurrently
+    #[serde(default = "now_ms")]
+    pub received_at_ms: u64,
+}
+
+impl Job {
+    /// A job for the current hook process
+    pub fn new(payload: String) -> Self {
+        Job { payload, context: DetectionContext::current(), decided: false, received_at_ms: now_ms() }
+    }
+}
+
+pub struct JobSpool {
+    base_dir: PathBuf,
+}
+
+impl JobSpool {
+    /// Jobs hold prompts and commands, so they're kept where only the user
+    /// can read them
+    pub fn new() -> Self {
+        Self::with_base_dir(private_dir::default_dir("jobs"))
+    }
+
+    pub fn with_base_dir(base_dir: PathBuf) -> Self {
+        private_dir::create(&base_dir).ok();
+        JobSpool { base_dir }
+    }
+
+    /// Write a job to disk, returning the path to hand to the delivery process
+    pub fn persist(&self, job: &Job) -> io::Result<PathBuf> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+        let name = format!("{}-{}.json", nanos, std::process::id());
+        let path = self.base_dir.join(name);
+        private_dir::write(&path, &serde_json::to_vec(job)?)?;
+        Ok(path)
+    }
+}
+
+impl Default for JobSpool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read a job and remove it so it's delivered at most once. The file is
+/// removed even if it can't be read, so failed jobs don't pile up.
+pub fn take_job(path: &Path) -> io::Result<Job> {
+    let contents = private_dir::read(path);
+    fs::remove_file(path).ok();
+    Ok(serde_json::from_slice(&contents?)?)
+}
+
+/// Start `command` in a session of its own with no stdio, without waiting for it.
+///
+/// Claude Code waits for the hook's stdout and stderr to close, so the child
+/// must not inherit them. Without a controlling terminal it also survives the
+/// hangup sent when the terminal Claude runs in is closed.
+pub fn spawn_detached(command: &mut Command) -> io::Result<()> {
+    // SAFETY: setsid is async-signal-safe and touches no memory of ours
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    fn test_job_round_trip() {
+        let dir = std::env::temp_dir().join(format!("claude-notifier-jobs-test-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        let spool = JobSpool::with_base_dir(dir.clone());
+
+        let path = spool.persist(&Job::new("{\"x\":1}".to_string())).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().mode() & 0o777, 0o600);
+        let job = take_job(&path).unwrap();
+
+        assert_eq!(job.payload, "{\"x\":1}");
+        assert_eq!(job.context.parent_pid, Some(std::os::unix::process::parent_id()));
+        assert!(!path.exists());
+        assert!(take_job(&path).is_err());
+
+        fs::write(&path, "not a job").unwrap();
+        assert!(take_job(&path).is_err());
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use crate::private_dir;
+use crate::template::format_timestamp;
+
+/// Claude Code treats this exit code as "block the action", so we never use it
+pub const BLOCKING_EXIT_CODE: u8 = 2;
+
+/// Everything that can go wrong while handling a hook
+#[derive(Debug)]
+pub enum NotifierError {
+    /// config.toml couldn't be read, parsed or validated
+    Config(String),
+    /// The hook payload on stdin wasn't valid JSON
+    Payload(serde_json::Error),
+    /// Reading stdin or spawning a helper process failed
+    Io(io::Error),
+    /// Every selected backend failed
+    Delivery(Vec<String>),
+    /// A bug caused a panic
+    Panic(String),
+}
+
+impl fmt::Display for NotifierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotifierError::Config(e) => write!(f, "Invalid configuration: {}", e),
+            NotifierError::Payload(e) => write!(f, "Invalid hook payload: {}", e),
+            NotifierError::Io(e) => write!(f, "I/O error: {}", e),
+            NotifierError::Delivery(errors) => write!(f, "All notification backends failed: {}", errors.join("; ")),
+            NotifierError::Panic(message) => write!(f, "Panicked: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for NotifierError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NotifierError::Payload(e) => Some(e),
+            NotifierError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for NotifierError {
+    fn from(e: io::Error) -> Self {
+        NotifierError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for NotifierError {
+    fn from(e: serde_json::Error) -> Self {
+        NotifierError::Payload(e)
+    }
+}
+
+/// The process exit code to use after a failure, steering clear of the blocking code
+pub fn failure_exit_code(configured: u8) -> u8 {
+    if configured == BLOCKING_EXIT_CODE {
+        1
+    } else {
+        configured
+    }
+}
+
+/// Append a timestamped line to the error log. Logging is best effort.
+pub fn log_error(log_file: &Path, message: &str) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    if let Some(parent) = log_file.parent().filter(|parent| !parent.exists()) {
+        private_dir::create(parent).ok();
+    }
+
+    // Never follow a link to someone else's file, or append to a log another
+    // user created first
+    let Ok(mut file) = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .mode(0o600)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(log_file)
+    else {
+        return;
+    };
+    if file.metadata().is_ok_and(|metadata| private_dir::is_ours(&metadata)) {
+        writeln!(file, "{} {}", format_timestamp(now), message).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_never_exits_with_blocking_code() {
+        assert_eq!(failure_exit_code(0), 0);
+        assert_eq!(failure_exit_code(1), 1);
+        assert_eq!(failure_exit_code(BLOCKING_EXIT_CODE), 1);
+    }
+
+    #[test]
+    fn test_log_never_follows_links() {
+        let dir = std::env::temp_dir().join(format!("claude-notifier-log-{}", std::process::id()));
+        let log = dir.join("claude-notifier.log");
+        log_error(&log, "first");
+        assert!(std::fs::read_to_string(&log).unwrap().ends_with(" first\n"));
+
+        let target = dir.join("target");
+        std::fs::write(&target, "").unwrap();
+        let link = dir.join("link.log");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        log_error(&link, "second");
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
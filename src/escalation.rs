@@ -0,0 +1,211 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::session_store::{PendingEscalation, SessionStore};
+use crate::types::NotificationData;
+
+/// Re-sending approval requests nobody answered, configured under `[escalation]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EscalationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Events that start an escalation
+    #[serde(default = "default_events")]
+    pub events: Vec<String>,
+    /// Sent in order until the session moves on
+    #[serde(default = "default_steps")]
+    pub steps: Vec<EscalationStep>,
+}
+
+impl Default for EscalationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            events: default_events(),
+            steps: default_steps(),
+        }
+    }
+}
+
+/// One rung of the ladder
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EscalationStep {
+    /// Minutes after the original notification
+    pub after_minutes: u64,
+    /// Replaces the original sound
+    #[serde(default)]
+    pub sound: Option<String>,
+    #[serde(default = "default_step_title")]
+    pub title: String,
+    /// Delivers through these backends instead of `notifications.backends`
+    #[serde(default)]
+    pub backends: Option<Vec<String>>,
+}
+
+/// A step that is due
+#[derive(Debug)]
+pub struct DueStep {
+    pub data: NotificationData,
+    pub backends: Option<Vec<String>>,
+    pub event: String,
+}
+
+impl EscalationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.steps.windows(2).any(|pair| pair[0].after_minutes >= pair[1].after_minutes) {
+            return Err("steps must have increasing after_minutes".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn applies_to(&self, event: &str) -> bool {
+        self.enabled && !self.steps.is_empty() && self.events.iter().any(|e| e == event)
+    }
+}
+
+/// Start escalating a notification that was just sent. Replaces any earlier
+/// escalation for the session.
+pub fn arm(store: &SessionStore, session_id: &str, event: &str, data: &NotificationData, now_ms: u64) -> bool {
+    store.update_session(session_id, |session| {
+        session.pending_escalation = Some(PendingEscalation {
+            armed_at_ms: now_ms,
+            next_step: 0,
+            event: event.to_string(),
+            title: data.title.clone(),
+            body: data.body.clone(),
+            sound: data.sound.clone(),
+        });
+    }).is_some()
+}
+
+/// The session moved on, so stop escalating
+pub fn cancel(store: &SessionStore, session_id: &str) {
+    let armed = store.get_session(session_id).is_some_and(|session| session.pending_escalation.is_some());
+    if armed {
+        store.update_session(session_id, |session| session.pending_escalation = None);
+    }
+}
+
+/// Take the next step of the escalation armed at `armed_at_ms` if it's due.
+///
+/// Returns `Err` with the time to wait if it isn't due yet, and `Ok(None)`
+/// once the escalation was cancelled, replaced or has run out of steps.
+pub fn next_step(
+    store: &SessionStore,
+    session_id: &str,
+    armed_at_ms: u64,
+    config: &EscalationConfig,
+    now_ms: u64,
+) -> Result<Option<DueStep>, Duration> {
+    store.update_session(session_id, |session| {
+        let Some(pending) = session.pending_escalation.as_mut().filter(|p| p.armed_at_ms == armed_at_ms) else {
+            return Ok(None);
+        };
+        let Some(step) = config.steps.get(pending.next_step) else {
+            session.pending_escalation = None;
+            return Ok(None);
+        };
+
+        let due_at_ms = armed_at_ms + step.after_minutes * 60_000;
+        if now_ms < due_at_ms {
+            return Err(Duration::from_millis(due_at_ms - now_ms));
+        }
+
+        pending.next_step += 1;
+        Ok(Some(DueStep {
+            data: NotificationData {
+                title: step.title.clone(),
+                body: format!("{}: {}", pending.title, pending.body),
+                sound: step.sound.clone().unwrap_or_else(|| pending.sound.clone()),
+            },
+            backends: step.backends.clone(),
+            event: pending.event.clone(),
+        }))
+    }).unwrap_or(Ok(None))
+}
+
+fn default_events() -> Vec<String> { vec!["Notification".to_string()] }
+fn default_step_title() -> String { "Claude Is Still Waiting".to_string() }
+
+fn default_steps() -> Vec<EscalationStep> {
+    vec![
+        EscalationStep {
+            after_minutes: 5,
+            sound: Some("Sosumi".to_string()),
+            title: default_step_title(),
+            backends: None,
+        },
+        EscalationStep {
+            after_minutes: 15,
+            sound: Some("Sosumi".to_string()),
+            title: default_step_title(),
+            backends: None,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::fixtures;
+
+    fn config() -> EscalationConfig {
+        toml::from_str(r#"
+            enabled = true
+            [[steps]]
+            after_minutes = 5
+            sound = "Sosumi"
+            [[steps]]
+            after_minutes = 15
+            backends = ["webhook"]
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_ladder_until_exhausted() {
+        let (store, dir) = fixtures::store("ladder");
+        let config = config();
+        assert!(arm(&store, "s1", "Notification", &fixtures::approval("Bash"), 0));
+
+        assert_eq!(next_step(&store, "s1", 0, &config, 60_000).unwrap_err(), Duration::from_secs(240));
+
+        let first = next_step(&store, "s1", 0, &config, 300_000).unwrap().unwrap();
+        assert_eq!(first.data.sound, "Sosumi");
+        assert_eq!(first.data.body, "Claude Needs Approval: Bash");
+        assert!(first.backends.is_none());
+
+        let second = next_step(&store, "s1", 0, &config, 900_000).unwrap().unwrap();
+        assert_eq!(second.data.sound, "Glass");
+        assert_eq!(second.backends, Some(vec!["webhook".to_string()]));
+
+        assert!(next_step(&store, "s1", 0, &config, 1_000_000).unwrap().is_none());
+        assert!(store.get_session("s1").unwrap().pending_escalation.is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_cancelled_or_replaced() {
+        let (store, dir) = fixtures::store("cancel");
+        let config = config();
+
+        arm(&store, "s1", "Notification", &fixtures::approval("Bash"), 0);
+        cancel(&store, "s1");
+        assert!(next_step(&store, "s1", 0, &config, 300_000).unwrap().is_none());
+
+        // A newer approval request takes over from the first watcher
+        arm(&store, "s1", "Notification", &fixtures::approval("Bash"), 0);
+        arm(&store, "s1", "Notification", &fixtures::approval("Bash"), 1_000);
+        assert!(next_step(&store, "s1", 0, &config, 300_000).unwrap().is_none());
+        assert!(next_step(&store, "s1", 1_000, &config, 301_000).unwrap().is_some());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(config().validate().is_ok());
+        let mut bad = config();
+        bad.steps[1].after_minutes = 5;
+        assert!(bad.validate().is_err());
+    }
+}
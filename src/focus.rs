@@ -0,0 +1,272 @@
+use std::process::Command;
+
+#[cfg(not(target_os = "macos"))]
+use serde_json::Value;
+
+use crate::terminal_detector::{DetectionContext, TerminalInfo};
+
+/// Variables the focus checks' tools connect with, passed on as the hook saw them
+const FOCUS_ENV_VARS: &[&str] = &["TMUX", "SWAYSOCK", "I3SOCK", "DISPLAY", "XAUTHORITY"];
+
+/// Whether the terminal described by `info` is the one the user is looking at.
+/// `ctx` is the hook's environment, which under the daemon isn't ours.
+///
+/// Any check that can't be performed counts as "not focused", so notifications
+/// are only ever dropped when we're sure.
+pub fn is_terminal_focused(info: &TerminalInfo, ctx: &DetectionContext) -> bool {
+    if let Some(ref pane) = info.tmux_pane {
+        if !tmux_pane_active(pane, ctx) {
+            return false;
+        }
+    }
+
+    is_window_focused(info, ctx)
+}
+
+/// `program`, run with the hook's display and window manager sockets rather
+/// than ours
+fn command(program: &str, ctx: &DetectionContext) -> Command {
+    let mut command = Command::new(program);
+    for name in FOCUS_ENV_VARS {
+        match ctx.var(name) {
+            Some(value) => command.env(name, value),
+            None => command.env_remove(name),
+        };
+    }
+    command
+}
+
+#[cfg(target_os = "macos")]
+fn is_window_focused(info: &TerminalInfo, ctx: &DetectionContext) -> bool {
+    use crate::terminal_detector::macos_app_name;
+
+    let Some(ref app) = info.terminal_app else {
+        return false;
+    };
+    let app = macos_app_name(app);
+
+    let script = r#"tell application "System Events" to get name of first application process whose frontmost is true"#;
+    if !osascript(script).is_some_and(|frontmost| frontmost.eq_ignore_ascii_case(app)) {
+        return false;
+    }
+
+    // The app is in front, but it may be showing another of its windows
+    match app {
+        "Terminal" | "iTerm" => {
+            let script = match app {
+                "Terminal" => r#"tell application "Terminal" to get tty of selected tab of front window"#,
+                _ => r#"tell application "iTerm" to get tty of current session of current window"#,
+            };
+            let front_tty = osascript(script);
+            front_tty.is_some() && front_tty == terminal_tty(info, ctx)
+        }
+        // Other apps can't say which window is ours, so only a lone window is sure
+        _ => {
+            let script = format!(r#"tell application "System Events" to count windows of application process "{}""#, app);
+            osascript(&script).as_deref() == Some("1")
+        }
+    }
+}
+
+/// The tty the terminal app shows the session in: tmux's client rather than
+/// the pane inside it
+#[cfg(target_os = "macos")]
+fn terminal_tty(info: &TerminalInfo, ctx: &DetectionContext) -> Option<String> {
+    let Some(ref pane) = info.tmux_pane else {
+        return info.tty.clone();
+    };
+    let output = command("tmux", ctx).args(["display-message", "-p", "-t", pane, "#{client_tty}"]).output().ok()?;
+    let tty = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !tty.is_empty()).then_some(tty)
+}
+
+#[cfg(target_os = "macos")]
+fn osascript(script: &str) -> Option<String> {
+    let output = Command::new("osascript").arg("-e").arg(script).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn is_window_focused(info: &TerminalInfo, ctx: &DetectionContext) -> bool {
+    if ctx.var("SWAYSOCK").is_some() {
+        return wm_tree_focused("swaymsg", info, ctx);
+    }
+    if ctx.var("I3SOCK").is_some() {
+        return wm_tree_focused("i3-msg", info, ctx);
+    }
+    if ctx.var("DISPLAY").is_some() {
+        return x11_focused(info, ctx);
+    }
+    false
+}
+
+/// Ask sway or i3 for the window tree and check its focused window
+#[cfg(not(target_os = "macos"))]
+fn wm_tree_focused(msg_binary: &str, info: &TerminalInfo, ctx: &DetectionContext) -> bool {
+    let output = match command(msg_binary, ctx).args(["-t", "get_tree"]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return false,
+    };
+
+    let Ok(tree) = serde_json::from_slice::<Value>(&output.stdout) else {
+        return false;
+    };
+
+    match find_focused_node(&tree) {
+        Some(node) => node_matches(node, info),
+        None => false,
+    }
+}
+
+/// Check `_NET_ACTIVE_WINDOW` on the X11 root window
+#[cfg(not(target_os = "macos"))]
+fn x11_focused(info: &TerminalInfo, ctx: &DetectionContext) -> bool {
+    let Some(active) = xprop(&["-root", "_NET_ACTIVE_WINDOW"], ctx).and_then(|out| parse_xprop_window(&out)) else {
+        return false;
+    };
+
+    if let Some(window_id) = info.window_id.as_deref().and_then(parse_window_id) {
+        return active == window_id;
+    }
+
+    // Fall back to the pid of the active window's owner
+    xprop(&["-id", &active.to_string(), "_NET_WM_PID"], ctx)
+        .and_then(|out| parse_xprop_cardinal(&out))
+        .is_some_and(|pid| info.ancestor_pids.contains(&pid))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn xprop(args: &[&str], ctx: &DetectionContext) -> Option<String> {
+    let output = command("xprop", ctx).args(args).output().ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        None
+    }
+}
+
+fn tmux_pane_active(pane: &str, ctx: &DetectionContext) -> bool {
+    let output = command("tmux", ctx)
+        .args(["display-message", "-p", "-t", pane, "#{pane_active} #{window_active} #{session_attached}"])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => parse_tmux_active(&String::from_utf8_lossy(&output.stdout)),
+        _ => false,
+    }
+}
+
+/// `1 1 1` means the pane is active in the active window of an attached session
+fn parse_tmux_active(output: &str) -> bool {
+    let mut fields = output.split_whitespace();
+    let pane_active = fields.next() == Some("1");
+    let window_active = fields.next() == Some("1");
+    let attached = fields.next().and_then(|n| n.parse::<u32>().ok()).unwrap_or(0) > 0;
+    pane_active && window_active && attached
+}
+
+#[cfg(not(target_os = "macos"))]
+/// Depth-first search of a sway/i3 tree for the focused leaf
+fn find_focused_node(node: &Value) -> Option<&Value> {
+    if node.get("focused").and_then(Value::as_bool) == Some(true) {
+        return Some(node);
+    }
+
+    ["nodes", "floating_nodes"].iter()
+        .filter_map(|key| node.get(*key).and_then(Value::as_array))
+        .flatten()
+        .find_map(find_focused_node)
+}
+
+#[cfg(not(target_os = "macos"))]
+/// sway reports the owning pid, i3 only the X11 window id
+fn node_matches(node: &Value, info: &TerminalInfo) -> bool {
+    if let Some(pid) = node.get("pid").and_then(Value::as_u64) {
+        if info.ancestor_pids.contains(&(pid as u32)) {
+            return true;
+        }
+    }
+
+    match (node.get("window").and_then(Value::as_u64), info.window_id.as_deref().and_then(parse_window_id)) {
+        (Some(window), Some(window_id)) => window == window_id,
+        _ => false,
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+/// Parse `_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007`
+fn parse_xprop_window(output: &str) -> Option<u64> {
+    output.rsplit('#').next()?
+        .split(',').next()
+        .and_then(|id| parse_window_id(id.trim()))
+        .filter(|&id| id != 0)
+}
+
+#[cfg(not(target_os = "macos"))]
+/// Parse `_NET_WM_PID(CARDINAL) = 4242`
+fn parse_xprop_cardinal(output: &str) -> Option<u32> {
+    output.rsplit('=').next()?.trim().parse().ok()
+}
+
+#[cfg(not(target_os = "macos"))]
+/// Window ids are decimal in `$WINDOWID` and hex in xprop output
+fn parse_window_id(id: &str) -> Option<u64> {
+    match id.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => id.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(target_os = "macos"))]
+    use serde_json::json;
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_parse_xprop() {
+        assert_eq!(parse_xprop_window("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007\n"), Some(0x3a00007));
+        assert_eq!(parse_xprop_window("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x0"), None);
+        assert_eq!(parse_xprop_cardinal("_NET_WM_PID(CARDINAL) = 4242\n"), Some(4242));
+        assert_eq!(parse_window_id("60817415"), Some(0x3a00007));
+    }
+
+    #[test]
+    fn test_tools_see_the_hooks_display() {
+        let mut ctx = DetectionContext::default();
+        ctx.env.insert("DISPLAY".to_string(), ":7".to_string());
+        let output = command("sh", &ctx).args(["-c", "echo \"$DISPLAY|$SWAYSOCK\""]).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), ":7|");
+    }
+
+    #[test]
+    fn test_parse_tmux_active() {
+        assert!(parse_tmux_active("1 1 1\n"));
+        assert!(!parse_tmux_active("0 1 1\n"));
+        assert!(!parse_tmux_active("1 1 0\n"));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_focused_node_in_tree() {
+        let tree = json!({
+            "focused": false,
+            "nodes": [
+                { "focused": false, "nodes": [ { "focused": false, "pid": 10, "nodes": [] } ] },
+                { "focused": false, "nodes": [], "floating_nodes": [
+                    { "focused": true, "pid": 20, "window": 60817415, "nodes": [] }
+                ] }
+            ]
+        });
+
+        let node = find_focused_node(&tree).unwrap();
+        let by_pid = TerminalInfo { ancestor_pids: vec![99, 20], ..TerminalInfo::default() };
+        let by_window = TerminalInfo { window_id: Some("60817415".to_string()), ..TerminalInfo::default() };
+        let other = TerminalInfo { ancestor_pids: vec![10], ..TerminalInfo::default() };
+
+        assert!(node_matches(node, &by_pid));
+        assert!(node_matches(node, &by_window));
+        assert!(!node_matches(node, &other));
+    }
+}
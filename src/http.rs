@@ -0,0 +1,307 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// An HTTP request, sent with `curl`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    /// `user:password` for basic auth
+    pub basic_auth: Option<String>,
+    pub timeout: Duration,
+    /// Carries a token from the config, so it's never written to the spool
+    #[serde(skip)]
+    pub secret: bool,
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Request {
+    pub fn new(method: &str, url: &str) -> Self {
+        Request {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: None,
+            basic_auth: None,
+            timeout: Duration::from_secs(10),
+            secret: false,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: String) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    pub fn json(self, value: &serde_json::Value) -> Self {
+        self.header("Content-Type", "application/json").body(value.to_string())
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn secret(mut self) -> Self {
+        self.secret = true;
+        self
+    }
+
+    /// Send the request. Any HTTP status counts as a response; only transport
+    /// failures are errors.
+    pub fn send(&self) -> Result<Response, String> {
+        let output = run_curl(&self.curl_config())
+            .map_err(|e| format!("Request to {} failed: {}", self.url, e))?;
+        parse_output(&output).ok_or_else(|| format!("Unexpected output from curl for {}", self.url))
+    }
+
+    fn curl_config(&self) -> String {
+        let mut lines = vec![
+            format!("url = {}", quote(&self.url)),
+            format!("request = {}", quote(&self.method)),
+            format!("max-time = {}", self.timeout.as_secs_f64()),
+            format!("write-out = {}", quote("\n%{http_code}")),
+        ];
+        for (name, value) in &self.headers {
+            lines.push(format!("header = {}", quote(&format!("{}: {}", name, value))));
+        }
+        if let Some(ref credentials) = self.basic_auth {
+            lines.push(format!("user = {}", quote(credentials)));
+        }
+        if let Some(ref body) = self.body {
+            // data-raw, unlike data, doesn't read a file when the body starts with @
+            lines.push(format!("data-raw = {}", quote(body)));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Worth trying again later: server errors and rate limiting
+    pub fn is_retryable(&self) -> bool {
+        self.status >= 500 || self.status == 429 || self.status == 408
+    }
+}
+
+/// Run curl with `config` as its config file, returning what it wrote to stdout.
+///
+/// Everything goes through a config on stdin so tokens and message bodies
+/// never show up in the process list.
+pub fn run_curl(config: &str) -> Result<String, String> {
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run curl: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(config.as_bytes()).map_err(|e| format!("Failed to write to curl: {}", e))?;
+    }
+
+    let output = child.wait_with_output().map_err(|e| format!("Failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Quote a value for a curl config file
+pub fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Split curl's output into the body and the status code it wrote last
+fn parse_output(output: &str) -> Option<Response> {
+    let (body, status) = output.rsplit_once('\n')?;
+    Some(Response { status: status.trim().parse().ok()?, body: body.to_string() })
+}
+
+#[cfg(test)]
+pub mod stub {
+    //! A minimal HTTP server for testing backends
+
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+
+    /// A request as the stub received it
+    #[derive(Debug)]
+    pub struct Received {
+        pub method: String,
+        pub path: String,
+        pub headers: Vec<(String, String)>,
+        pub body: String,
+    }
+
+    impl Received {
+        pub fn header(&self, name: &str) -> Option<&str> {
+            self.headers.iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    /// Answer one request per entry in `responses` with that status and body,
+    /// returning the base URL and the requests received
+    pub fn serve(responses: Vec<(u16, String)>) -> (String, Receiver<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for (status, body) in responses {
+                let Ok((stream, _)) = listener.accept() else { return };
+                let mut reader = BufReader::new(stream);
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).ok();
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let path = parts.next().unwrap_or_default().to_string();
+
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((key, value)) = line.trim_end().split_once(':') {
+                        headers.push((key.to_string(), value.trim().to_string()));
+                    }
+                }
+
+                let length = headers.iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, value)| value.parse().ok())
+                    .unwrap_or(0);
+                let mut content = vec![0; length];
+                reader.read_exact(&mut content).ok();
+
+                let response = format!(
+                    "HTTP/1.1 {} Stub\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status, body.len(), body
+                );
+                reader.get_mut().write_all(response.as_bytes()).ok();
+
+                let body = String::from_utf8_lossy(&content).to_string();
+                if sender.send(Received { method, path, headers, body }).is_err() {
+                    return;
+                }
+            }
+        });
+
+        (url, receiver)
+    }
+}
+
+#[cfg(test)]
+pub mod fixtures {
+    //! Configs, notifications and stores the tests share
+
+    use std::path::PathBuf;
+
+    use serde::de::DeserializeOwned;
+    use serde_json::Value;
+
+    use crate::session_store::SessionStore;
+    use crate::terminal_detector::TerminalInfo;
+    use crate::types::{HookPayload, NotificationData};
+
+    /// A backend config from TOML, with `{url}` standing for the stub's base URL
+    pub fn config<T: DeserializeOwned>(url: &str, toml: &str) -> T {
+        toml::from_str(&toml.replace("{url}", url)).unwrap()
+    }
+
+    /// An approval request, as a `Notification` hook notifies it
+    pub fn approval(body: &str) -> NotificationData {
+        NotificationData { title: "Claude Needs Approval".to_string(), body: body.to_string(), sound: "Glass".to_string() }
+    }
+
+    /// A finished session, as a `Stop` hook notifies it
+    pub fn finished(body: &str) -> NotificationData {
+        NotificationData { title: "Claude Finished".to_string(), body: body.to_string(), sound: "Hero".to_string() }
+    }
+
+    pub fn payload(value: Value) -> HookPayload {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// A store with session `s1` in a directory of its own, to remove at the end
+    pub fn store(name: &str) -> (SessionStore, PathBuf) {
+        let dir = std::env::temp_dir()
+            .join(format!("claude-notifier-store-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let store = SessionStore::with_base_dir(dir.clone());
+        store.store_session("s1", TerminalInfo::default(), None, None).unwrap();
+        (store, dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_through_curl() {
+        let (url, received) = stub::serve(vec![(201, "created".to_string())]);
+
+        let response = Request::new("POST", &format!("{}/hook?x=1", url))
+            .header("X-Token", "secret \"quoted\"")
+            .body("@not-a-file\nline two".to_string())
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status, 201);
+        assert_eq!(response.body, "created");
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/hook?x=1");
+        assert_eq!(request.header("X-Token"), Some("secret \"quoted\""));
+        assert_eq!(request.body, "@not-a-file\nline two");
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let response = parse_output("{\"ok\":true}\n200").unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "{\"ok\":true}");
+        assert!(parse_output("garbage").is_none());
+    }
+}
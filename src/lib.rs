@@ -1,108 +1,172 @@
 pub mod types;
+pub mod error;
 pub mod terminal_detector;
 pub mod session_store;
 pub mod terminal_notifier;
+pub mod backends;
+pub mod template;
+pub mod rules;
+pub mod schedule;
+pub mod debounce;
+pub mod focus;
+pub mod detach;
+pub mod daemon;
+pub mod permission;
+pub mod policy;
+pub mod escalation;
+pub mod http;
+pub mod private_dir;
+pub mod lock;
 
-use types::{Config, HookPayload, NotificationData};
+use serde_json::Value;
+use template::truncate;
+use types::{Config, HookEvent, HookPayload, NotificationData};
+
+/// Whether the config allows notifications for this payload's event
+pub fn should_notify(payload: &HookPayload, config: &Config) -> bool {
+    config.notifications.events.is_enabled(payload.event.name())
+}
 
 pub fn process_hook_event(payload: &HookPayload, config: &Config) -> NotificationData {
-    match payload.event.as_str() {
-        "Notification" => {
-            let body = if let Some(metadata) = &payload.metadata {
-                if let Some(tool_name) = &metadata.tool_name {
-                    format!("Claude needs approval to use: {tool_name}")
-                } else if let Some(message) = &metadata.message {
-                    message.clone()
-                } else {
-                    "Claude needs your approval".to_string()
-                }
+    let sounds = &config.notifications.sounds;
+
+    match &payload.event {
+        HookEvent::Notification(event) => {
+            let body = if let Some(tool_name) = &event.tool_name {
+                format!("Claude needs approval to use: {tool_name}")
+            } else if let Some(message) = &event.message {
+                message.clone()
             } else {
                 "Claude needs your approval".to_string()
             };
-            
+
             NotificationData {
                 title: "Claude Needs Approval".to_string(),
                 body,
-                sound: config.notifications.sounds.approval.clone(),
+                sound: sounds.approval.clone(),
             }
         },
-        "PreToolUse" => {
-            let body = if let Some(metadata) = &payload.metadata {
-                match (&metadata.tool_name, &metadata.command) {
-                    (Some(tool_name), Some(command)) if tool_name == "Bash" => {
-                        format!("Running: {command}")
-                    },
-                    (Some(tool_name), _) => {
-                        format!("Using tool: {tool_name}")
-                    },
-                    _ => "Using tool".to_string(),
-                }
-            } else if let Some(tool_name) = &payload.tool_name {
-                // Check for tool_name at top level (new format)
-                if tool_name == "Bash" {
-                    if let Some(tool_input) = &payload.tool_input {
-                        if let Some(command) = tool_input.get("command").and_then(|v| v.as_str()) {
-                            format!("Running: {command}")
-                        } else {
-                            format!("Using tool: {tool_name}")
-                        }
-                    } else {
-                        format!("Using tool: {tool_name}")
-                    }
-                } else {
-                    format!("Using tool: {tool_name}")
-                }
-            } else if let Some(content) = &payload.content {
-                if let Some(tool_name) = content.get("tool_name").and_then(|v| v.as_str()) {
-                    if tool_name == "Bash" {
-                        if let Some(params) = content.get("parameters") {
-                            if let Some(cmd) = params.get("command").and_then(|v| v.as_str()) {
-                                format!("Running: {cmd}")
-                            } else {
-                                format!("Using tool: {tool_name}")
-                            }
-                        } else {
-                            format!("Using tool: {tool_name}")
-                        }
-                    } else {
-                        format!("Using tool: {tool_name}")
-                    }
-                } else {
-                    "Using tool".to_string()
-                }
-            } else {
-                "Using tool".to_string()
+        HookEvent::PreToolUse(event) => {
+            let body = match (event.tool_name.as_deref(), payload.event.command()) {
+                (Some("Bash"), Some(command)) => format!("Running: {command}"),
+                (Some(tool_name), _) => format!("Using tool: {tool_name}"),
+                _ => "Using tool".to_string(),
             };
-            
+
             NotificationData {
                 title: "Claude Tool Use".to_string(),
                 body,
-                sound: config.notifications.sounds.tool_use.clone(),
+                sound: sounds.tool_use.clone(),
+            }
+        },
+        HookEvent::PostToolUse(event) => {
+            let tool_name = event.tool_name.as_deref().unwrap_or("tool");
+            let command = payload.event.command().filter(|_| tool_name == "Bash");
+            let failure = event.tool_response.as_ref().and_then(tool_failure);
+
+            match failure {
+                Some(error) => NotificationData {
+                    title: "Claude Tool Failed".to_string(),
+                    body: if error.is_empty() {
+                        format!("{tool_name} failed")
+                    } else {
+                        format!("{tool_name} failed: {error}")
+                    },
+                    sound: sounds.tool_failure.clone(),
+                },
+                None => NotificationData {
+                    title: "Claude Tool Finished".to_string(),
+                    body: match command {
+                        Some(command) => format!("Finished: {command}"),
+                        None => format!("Finished using tool: {tool_name}"),
+                    },
+                    sound: sounds.tool_result.clone(),
+                },
             }
         },
-        "Stop" => {
-            let body = if let Some(metadata) = &payload.metadata {
-                if let Some(desc) = &metadata.description {
-                    desc.clone()
-                } else if let Some(message) = &metadata.message {
-                    message.clone()
-                } else {
-                    "Task completed".to_string()
-                }
+        HookEvent::UserPromptSubmit(event) => NotificationData {
+            title: "Prompt Submitted".to_string(),
+            body: event.prompt.as_deref()
+                .map(|prompt| truncate(prompt, 100))
+                .unwrap_or_else(|| "Prompt submitted".to_string()),
+            sound: sounds.prompt_submit.clone(),
+        },
+        HookEvent::Stop(event) => {
+            let body = if let Some(desc) = &event.description {
+                desc.clone()
+            } else if let Some(message) = &event.message {
+                message.clone()
             } else {
                 "Task completed".to_string()
             };
-            
+
             NotificationData {
                 title: "Claude Finished".to_string(),
                 body,
-                sound: config.notifications.sounds.completion.clone(),
+                sound: sounds.completion.clone(),
             }
         },
-        _ => NotificationData {
+        HookEvent::SubagentStop(_) => NotificationData {
+            title: "Claude Subagent Finished".to_string(),
+            body: "Subagent task completed".to_string(),
+            sound: sounds.subagent_completion.clone(),
+        },
+        HookEvent::PreCompact(event) => NotificationData {
+            title: "Claude Compacting Context".to_string(),
+            body: match event.trigger.as_deref() {
+                Some(trigger) => format!("Compacting conversation ({trigger})"),
+                None => "Compacting conversation".to_string(),
+            },
+            sound: sounds.compact.clone(),
+        },
+        HookEvent::SessionStart(event) => NotificationData {
+            title: "Claude Session Started".to_string(),
+            body: match event.source.as_deref() {
+                Some(source) => format!("Session started ({source})"),
+                None => "Session started".to_string(),
+            },
+            sound: sounds.session_start.clone(),
+        },
+        HookEvent::SessionEnd(event) => NotificationData {
+            title: "Claude Session Ended".to_string(),
+            body: match event.reason.as_deref() {
+                Some(reason) => format!("Session ended ({reason})"),
+                None => "Session ended".to_string(),
+            },
+            sound: sounds.session_end.clone(),
+        },
+        HookEvent::Unknown(_) => NotificationData {
             title: "Claude Event".to_string(),
-            body: format!("Event: {}", payload.event),
-            sound: config.notifications.sounds.unknown.clone(),
+            body: format!("Event: {}", payload.event.name()),
+            sound: sounds.unknown.clone(),
         },
     }
-}
\ No newline at end of file
+}
+
+/// Whether the payload reports a tool call that failed
+pub fn is_tool_failure(payload: &HookPayload) -> bool {
+    match &payload.event {
+        HookEvent::PostToolUse(event) => event.tool_response.as_ref().and_then(tool_failure).is_some(),
+        _ => false,
+    }
+}
+
+/// Returns the error message if a `tool_response` reports a failed tool call
+fn tool_failure(response: &Value) -> Option<String> {
+    let failed = response.get("success").and_then(Value::as_bool) == Some(false)
+        || response.get("is_error").and_then(Value::as_bool) == Some(true)
+        || response.get("interrupted").and_then(Value::as_bool) == Some(true)
+        || response.get("error").is_some_and(|e| !e.is_null());
+
+    if !failed {
+        return None;
+    }
+
+    let message = ["error", "stderr", "message"].iter()
+        .filter_map(|key| response.get(*key).and_then(Value::as_str))
+        .map(str::trim)
+        .find(|msg| !msg.is_empty())
+        .unwrap_or("");
+
+    Some(truncate(message, 100))
+}
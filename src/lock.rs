@@ -0,0 +1,53 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// An exclusive `flock` on a file, held until dropped
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Wait for the lock on `path`, creating the file if needed
+    pub fn acquire(path: &Path) -> io::Result<Self> {
+        Self::lock(path, libc::LOCK_EX).map(|lock| lock.expect("blocking flock returned without the lock"))
+    }
+
+    /// Take the lock on `path` if nobody holds it
+    pub fn try_acquire(path: &Path) -> io::Result<Option<Self>> {
+        Self::lock(path, libc::LOCK_EX | libc::LOCK_NB)
+    }
+
+    fn lock(path: &Path, operation: libc::c_int) -> io::Result<Option<Self>> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).mode(0o600).open(path)?;
+        loop {
+            // SAFETY: the descriptor is open for as long as `file` lives
+            if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+                return Ok(Some(FileLock { _file: file }));
+            }
+            let error = io::Error::last_os_error();
+            match error.kind() {
+                io::ErrorKind::Interrupted => continue,
+                io::ErrorKind::WouldBlock => return Ok(None),
+                _ => return Err(error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let path = std::env::temp_dir().join(format!("claude-notifier-lock-{}", std::process::id()));
+        let held = FileLock::acquire(&path).unwrap();
+        assert!(FileLock::try_acquire(&path).unwrap().is_none());
+        drop(held);
+        assert!(FileLock::try_acquire(&path).unwrap().is_some());
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -1,21 +1,71 @@
-use claude_notifier::types::{Config, HookPayload};
-use claude_notifier::{process_hook_event, terminal_detector::TerminalInfo, session_store::SessionStore, terminal_notifier};
+use claude_notifier::types::{Config, HookEvent, HookPayload, NotificationData};
+use claude_notifier::{process_hook_event, should_notify, terminal_detector::TerminalInfo, session_store::SessionStore};
+use claude_notifier::backends::{self, mqtt, pushover, smtp, telegram, DeliveryContext, DeliveryReport};
+use claude_notifier::debounce::{self, DebounceDecision};
+use claude_notifier::daemon;
+use claude_notifier::detach::{self, Job, JobSpool};
+use claude_notifier::escalation;
+use claude_notifier::focus;
+use claude_notifier::permission::{self, Decision};
+use claude_notifier::policy::{self, PolicyOutcome};
+use claude_notifier::rules::{self, RuleAction};
+use claude_notifier::schedule::ScheduleDecision;
+use claude_notifier::template::apply_templates;
+use claude_notifier::error::{failure_exit_code, log_error, NotifierError};
+use std::any::Any;
 use std::io::{self, Read};
+use std::panic::{self, AssertUnwindSafe};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::env;
+use std::process::{Command, ExitCode};
+use std::thread;
+use std::time::{Duration, Instant};
 
 pub fn load_config() -> Config {
+    match try_load_config() {
+        Ok((config, errors)) => {
+            // Only the sections with a bad value fall back to their defaults
+            for error in errors {
+                report_error(&config, &NotifierError::Config(format!("{}; using the defaults for it", error)));
+            }
+            config
+        }
+        Err(e) => {
+            // A config that doesn't parse at all shouldn't cost the user their notifications
+            let config = Config::default();
+            report_error(&config, &e);
+            config
+        }
+    }
+}
+
+/// The config, and why any sections of it were dropped
+fn try_load_config() -> Result<(Config, Vec<String>), NotifierError> {
     // Try to find config.toml in multiple locations
     let config_path = find_config_file().unwrap_or_else(|| PathBuf::from("config.toml"));
 
-    if config_path.exists() {
-        let contents = fs::read_to_string(&config_path)
-            .expect("Failed to read config.toml");
-        toml::from_str(&contents)
-            .expect("Failed to parse config.toml")
-    } else {
-        Config::default()
+    if !config_path.exists() {
+        return Ok((Config::default(), Vec::new()));
+    }
+
+    let contents = fs::read_to_string(&config_path)
+        .map_err(|e| NotifierError::Config(format!("Failed to read {}: {}", config_path.display(), e)))?;
+    let mut config: Config = toml::from_str(&contents)
+        .map_err(|e| NotifierError::Config(format!("Failed to parse {}: {}", config_path.display(), e)))?;
+    let errors = config.drop_invalid_sections().into_iter()
+        .map(|e| format!("Invalid {}: {}", config_path.display(), e))
+        .collect();
+    Ok((config, errors))
+}
+
+/// Record a failure without disturbing Claude Code
+fn report_error(config: &Config, error: &NotifierError) {
+    if let Some(path) = config.hook.log_path() {
+        log_error(&path, &error.to_string());
+    }
+    if config.debug.enabled {
+        eprintln!("Error: {}", error);
     }
 }
 
@@ -49,9 +99,50 @@ fn find_config_file() -> Option<PathBuf> {
     None
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> ExitCode {
+    let started = Instant::now();
+    let args: Vec<String> = env::args().collect();
     let config = load_config();
 
+    // Panics are reported through the log like any other failure
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| match args.get(1).map(String::as_str) {
+        Some("--flush-summary") if args.len() == 3 => flush_summary(&config, &args[2]),
+        Some("--escalate") if args.len() == 4 => escalate(&config, &args[2], &args[3]),
+        Some("--deliver") if args.len() == 3 => deliver_job(&config, Path::new(&args[2])),
+        Some("daemon") if args.len() == 2 => run_daemon(config.clone()),
+        _ => {
+            let result = run(&config);
+            report_budget(&config, started.elapsed());
+            result
+        }
+    }));
+
+    match result.unwrap_or_else(|panic| Err(NotifierError::Panic(panic_message(panic)))) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            report_error(&config, &e);
+            ExitCode::from(failure_exit_code(config.hook.exit_code_on_error))
+        }
+    }
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => panic.downcast_ref::<&str>().map(|s| s.to_string()).unwrap_or_else(|| "unknown panic".to_string()),
+    }
+}
+
+fn report_budget(config: &Config, elapsed: Duration) {
+    if config.debug.enabled {
+        let budget = Duration::from_millis(config.hook.budget_ms);
+        let verdict = if elapsed > budget { "over" } else { "within" };
+        eprintln!("Debug: Hook took {}ms, {} the {}ms budget", elapsed.as_millis(), verdict, budget.as_millis());
+    }
+}
+
+fn run(config: &Config) -> Result<(), NotifierError> {
     let mut buffer = String::new();
     io::stdin().read_to_string(&mut buffer)?;
 
@@ -61,10 +152,257 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let payload: HookPayload = serde_json::from_str(&buffer)?;
 
-    // Store session info if we have a session ID
+    // A decision has to come from this process, since it's printed on our stdout
+    if matches!(payload.event, HookEvent::PreToolUse(_)) {
+        let project_dir = env::var("CLAUDE_PROJECT_DIR").ok().or_else(|| payload.cwd.clone());
+        let decided = if let Some(outcome) = config.policy.evaluate(&payload, project_dir.as_deref()) {
+            Some(apply_policy(config, &payload, &outcome))
+        } else if config.permissions.should_ask(payload.event.tool_name()) {
+            Some(ask_permission(config, &payload))
+        } else {
+            None
+        };
+
+        // The session still moved on, whatever was decided
+        if let Some(result) = decided {
+            hand_off(config, &Job { decided: true, ..Job::new(buffer) }, &payload)?;
+            return result;
+        }
+    }
+
+    hand_off(config, &Job::new(buffer), &payload)
+}
+
+/// Deliver through the daemon, a detached process or ourselves, whichever is
+/// available first
+fn hand_off(config: &Config, job: &Job, payload: &HookPayload) -> Result<(), NotifierError> {
+    let socket = config.daemon.socket_path();
+    if socket.exists() {
+        match daemon::submit(&socket, job, config.daemon.timeout()) {
+            Ok(()) => {
+                if config.debug.enabled {
+                    eprintln!("Debug: Handed off {} to the daemon", payload.event.name());
+                }
+                return Ok(());
+            }
+            Err(e) if config.debug.enabled => eprintln!("Debug: Daemon unavailable, delivering ourselves: {}", e),
+            Err(_) => {}
+        }
+    }
+
+    if config.hook.detach {
+        // Detection and delivery spawn processes of their own; none of that has to hold up Claude
+        let path = JobSpool::new().persist(job)?;
+        let spawned = env::current_exe()
+            .and_then(|exe| detach::spawn_detached(Command::new(exe).arg("--deliver").arg(&path)));
+        if let Err(e) = spawned {
+            fs::remove_file(&path).ok();
+            return Err(e.into());
+        }
+
+        if config.debug.enabled {
+            eprintln!("Debug: Handed off delivery of {}", payload.event.name());
+        }
+        return Ok(());
+    }
+
+    deliver(config, &SessionStore::new(), payload, job, true)
+}
+
+/// Print the policy's decision, record it and explain it if configured to
+fn apply_policy(config: &Config, payload: &HookPayload, outcome: &PolicyOutcome) -> Result<(), NotifierError> {
+    println!("{}", permission::hook_output(outcome.decision, &outcome.reason));
+    policy::record(&SessionStore::new(), payload, outcome, config.policy.audit_limit, debounce::now_ms());
+
+    if config.debug.enabled {
+        eprintln!("Debug: Policy decision: {} ({})", outcome.decision.as_str(), outcome.reason);
+    }
+
+    if !config.policy.notify_on.contains(&outcome.decision) {
+        return Ok(());
+    }
+
+    let data = policy::decision_notification(payload, outcome, config);
+    let ctx = DeliveryContext {
+        config,
+        session_id: payload.session_id.as_deref(),
+        event: payload.event.name(),
+        payload: Some(payload),
+        inline: true,
+    };
+    check_delivery(config, backends::dispatch(&data, &ctx))
+}
+
+/// Block on Allow/Deny buttons and print the decision for Claude Code
+fn ask_permission(config: &Config, payload: &HookPayload) -> Result<(), NotifierError> {
+    let permissions = &config.permissions;
+    let mut data = apply_templates(process_hook_event(payload, config), payload, None, config);
+    data.title = "Claude Needs Approval".to_string();
+    data.sound = config.notifications.sounds.approval.clone();
+
+    let ctx = DeliveryContext {
+        config,
+        session_id: payload.session_id.as_deref(),
+        event: payload.event.name(),
+        payload: Some(payload),
+        inline: true,
+    };
+    let choice = backends::ask_named(&config.notifications.backends, &data, &permissions.choices(), permissions.timeout(), &ctx)
+        .map_err(|e| NotifierError::Delivery(vec![e]))?;
+
+    let Some(decision) = permissions.decision_for_choice(choice) else {
+        if config.debug.enabled {
+            eprintln!("Debug: No answer, leaving the decision to Claude Code");
+        }
+        return Ok(());
+    };
+
+    let reason = match (choice, decision) {
+        (None, _) => format!("No answer from the user within {}s", permissions.timeout_secs),
+        (Some(_), Decision::Allow) => "Allowed by the user from a notification".to_string(),
+        (Some(_), _) => "Denied by the user from a notification".to_string(),
+    };
+    println!("{}", permission::hook_output(decision, &reason));
+    Ok(())
+}
+
+/// Serve hooks over the daemon socket, loading config and scanning for tools only once
+fn run_daemon(config: Config) -> Result<(), NotifierError> {
+    let socket = config.daemon.socket_path();
+    let listener = daemon::bind(&socket)?;
+    if config.debug.enabled {
+        eprintln!("Debug: Listening on {}", socket.display());
+    }
+
+    // Answers to Telegram approval buttons arrive by long polling
+    if config.backends.telegram.is_configured() {
+        let telegram_config = config.backends.telegram.clone();
+        let error_config = config.clone();
+        thread::spawn(move || {
+            telegram::poll_forever(telegram_config, |e| {
+                report_error(&error_config, &NotifierError::Delivery(vec![format!("telegram: {}", e)]))
+            })
+        });
+    }
+
+    let store = SessionStore::new();
+    thread::spawn(|| loop {
+        thread::sleep(Duration::from_secs(3600));
+        SessionStore::open().cleanup();
+    });
+
+    daemon::serve(listener, move |job, payload| {
+        // One bad payload mustn't take the daemon down
+        let result = panic::catch_unwind(AssertUnwindSafe(|| deliver(&config, &store, &payload, &job, false)));
+        if let Err(e) = result.unwrap_or_else(|panic| Err(NotifierError::Panic(panic_message(panic)))) {
+            report_error(&config, &e);
+        }
+    })?;
+
+    Ok(())
+}
+
+/// Deliver a job persisted by a detached hook
+fn deliver_job(config: &Config, path: &Path) -> Result<(), NotifierError> {
+    let job = detach::take_job(path)?;
+    let payload: HookPayload = serde_json::from_str(&job.payload)?;
+    deliver(config, &SessionStore::new(), &payload, &job, false)
+}
+
+/// Notify about a hook event; `inline` when the hook does it itself rather
+/// than a detached process or the daemon
+fn deliver(config: &Config, store: &SessionStore, payload: &HookPayload, job: &Job, inline: bool) -> Result<(), NotifierError> {
+    let terminal_info = record_activity(config, store, payload, job);
+
+    if !should_notify(payload, config) {
+        if config.debug.enabled {
+            eprintln!("Debug: Notifications disabled for {}", payload.event.name());
+        }
+        return Ok(());
+    }
+
+    let notification_data = process_hook_event(payload, config);
+    let notification_data = apply_templates(notification_data, payload, terminal_info.as_ref(), config);
+
+    // Digests cover everything the session did, including what's filtered below
+    if let (true, Some(session_id)) = (config.backends.smtp.wants_history(), &payload.session_id) {
+        smtp::record(store, session_id, payload.event.name(), &notification_data, debounce::now_ms());
+    }
+
+    if job.decided {
+        if config.debug.enabled {
+            eprintln!("Debug: {} was decided by the hook, nothing more to send", payload.event.name());
+        }
+        return Ok(());
+    }
+
+    let Some((notification_data, backend_names)) = route(config, payload, notification_data) else {
+        return Ok(());
+    };
+
+    // Only pay for the focus check when it can change the outcome
+    if !config.notifications.notify_when_focused.is_enabled(payload.event.name()) {
+        if let Some(ref info) = terminal_info {
+            if focus::is_terminal_focused(info, &job.context) {
+                if config.debug.enabled {
+                    eprintln!("Debug: Terminal is focused, skipping notification");
+                }
+                return Ok(());
+            }
+        }
+    }
+
     if let Some(ref session_id) = payload.session_id {
-        let store = SessionStore::new();
-        let terminal_info = TerminalInfo::detect();
+        let decision = debounce::check(
+            store,
+            session_id,
+            payload.event.name(),
+            payload.event.tool_name(),
+            &notification_data,
+            &config.notifications.debounce,
+            debounce::now_ms(),
+        );
+
+        if let DebounceDecision::Suppress { starts_burst } = decision {
+            if config.debug.enabled {
+                eprintln!("Debug: Debounced repeat notification");
+            }
+            // The first notification held back starts a process that sends the summary
+            if starts_burst && config.notifications.debounce.summarize {
+                detach::spawn_detached(Command::new(env::current_exe()?).arg("--flush-summary").arg(session_id))?;
+            }
+            return Ok(());
+        }
+    }
+
+    let ctx = DeliveryContext {
+        config,
+        session_id: payload.session_id.as_deref(),
+        event: payload.event.name(),
+        payload: Some(payload),
+        inline,
+    };
+    let reports = backends::dispatch_named(&backend_names, &notification_data, &ctx);
+
+    if let Some(ref session_id) = payload.session_id {
+        if config.escalation.applies_to(payload.event.name()) {
+            let armed_at_ms = debounce::now_ms();
+            if escalation::arm(store, session_id, payload.event.name(), &notification_data, armed_at_ms) {
+                detach::spawn_detached(
+                    Command::new(env::current_exe()?).arg("--escalate").arg(session_id).arg(armed_at_ms.to_string())
+                )?;
+            }
+        }
+    }
+
+    check_delivery(config, reports)
+}
+
+/// Record what the session is doing, whether or not anything is sent: its
+/// terminal, the end of any escalation, and its MQTT state
+fn record_activity(config: &Config, store: &SessionStore, payload: &HookPayload, job: &Job) -> Option<TerminalInfo> {
+    let terminal_info = payload.session_id.as_ref().map(|session_id| {
+        let terminal_info = TerminalInfo::detect_in(&job.context);
 
         if config.debug.enabled {
             eprintln!("Debug: Session ID: {}", session_id);
@@ -74,22 +412,164 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         // Store the session with terminal info
         store.store_session(
             session_id,
-            terminal_info,
+            terminal_info.clone(),
             payload.cwd.clone(),
             payload.transcript_path.clone()
         ).ok();
+
+        // Any activity means the session is no longer waiting on the user
+        escalation::cancel(store, session_id);
+        if config.backends.pushover.emergency {
+            pushover::cancel_emergency(&config.backends.pushover, store, session_id);
+        }
+
+        terminal_info
+    });
+
+    // State follows every event, whether or not it notifies
+    if config.backends.mqtt.tracks_state() && payload.session_id.is_some() {
+        if let Err(e) = mqtt::publish_state(&config.backends.mqtt, store, payload, job.received_at_ms) {
+            if config.debug.enabled {
+                eprintln!("Debug: Failed to publish MQTT state: {}", e);
+            }
+        }
+    }
+
+    terminal_info
+}
+
+/// Apply the first matching rule and the schedule to a notification. Returns
+/// it with the backends it should go to, or `None` if it's suppressed.
+fn route(config: &Config, payload: &HookPayload, mut data: NotificationData) -> Option<(NotificationData, Vec<String>)> {
+    let mut backend_names = &config.notifications.backends;
+
+    if let Some(rule) = rules::find_rule(&config.rules, payload) {
+        if rule.action == RuleAction::Suppress {
+            if config.debug.enabled {
+                eprintln!("Debug: Suppressed by rule: {:?}", rule);
+            }
+            return None;
+        }
+
+        data = rule.apply(data);
+        if let Some(ref names) = rule.backends {
+            backend_names = names;
+        }
     }
 
-    let notification_data = process_hook_event(&payload, &config);
-    terminal_notifier::send_notification(&notification_data, &config, payload.session_id)?;
+    match config.schedule.decide_now(payload.event.name()) {
+        ScheduleDecision::Deliver => Some((data, backend_names.clone())),
+        ScheduleDecision::Suppress => {
+            if config.debug.enabled {
+                eprintln!("Debug: Suppressed during quiet hours");
+            }
+            None
+        }
+        ScheduleDecision::Silent => {
+            data.sound.clear();
+            Some((data, backend_names.clone()))
+        }
+        ScheduleDecision::Route(names) => Some((data, names)),
+    }
+}
+
+/// Only a total failure counts as an error; partial failures show up in debug output
+fn check_delivery(config: &Config, reports: Vec<DeliveryReport>) -> Result<(), NotifierError> {
+    if config.debug.enabled {
+        for report in &reports {
+            match &report.result {
+                Ok(()) => eprintln!("Debug: Delivered via {}", report.backend),
+                Err(e) => eprintln!("Debug: Failed to deliver via {}: {}", report.backend, e),
+            }
+        }
+    }
+
+    if !reports.is_empty() && reports.iter().all(|r| !r.is_success()) {
+        let errors = reports.into_iter()
+            .filter_map(|r| r.result.err().map(|e| format!("{}: {}", r.backend, e)))
+            .collect();
+        return Err(NotifierError::Delivery(errors));
+    }
 
     Ok(())
 }
 
+/// Wait for the session's debounced bursts to end, sending each one's summary
+fn flush_summary(config: &Config, session_id: &str) -> Result<(), NotifierError> {
+    let store = SessionStore::new();
+    let debounce_config = &config.notifications.debounce;
+
+    // Give up eventually if the session never goes quiet
+    let deadline = Instant::now() + Duration::from_secs(3600);
+
+    let mut result = Ok(());
+    while Instant::now() < deadline {
+        let (summaries, wait) = debounce::take_summaries(&store, session_id, debounce_config, debounce::now_ms());
+        for summary in summaries {
+            // Routed like the events it stands for, as of now
+            let session = store.get_session(session_id);
+            let payload = HookPayload {
+                session_id: Some(session_id.to_string()),
+                transcript_path: session.as_ref().and_then(|s| s.transcript_path.clone()),
+                cwd: session.and_then(|s| s.cwd),
+                event: HookEvent::Unknown(serde_json::json!({ "hook_event_name": summary.event })),
+            };
+            let data = debounce::summary_notification(&summary, config);
+            let Some((data, backend_names)) = route(config, &payload, data) else { continue };
+
+            let ctx = DeliveryContext {
+                config,
+                session_id: Some(session_id),
+                event: &summary.event,
+                payload: None,
+                inline: false,
+            };
+            // One failed summary mustn't hold back the others
+            if let Err(e) = check_delivery(config, backends::dispatch_named(&backend_names, &data, &ctx)) {
+                result = Err(e);
+            }
+        }
+
+        match wait {
+            Some(wait) => thread::sleep(wait),
+            None => return result,
+        }
+    }
+
+    result
+}
+
+/// Walk an unanswered request up the escalation ladder until the session moves on
+fn escalate(config: &Config, session_id: &str, armed_at_ms: &str) -> Result<(), NotifierError> {
+    let armed_at_ms: u64 = armed_at_ms.parse()
+        .map_err(|_| NotifierError::Config(format!("Invalid escalation id: {}", armed_at_ms)))?;
+    let store = SessionStore::open();
+
+    loop {
+        match escalation::next_step(&store, session_id, armed_at_ms, &config.escalation, debounce::now_ms()) {
+            Err(wait) => thread::sleep(wait),
+            Ok(None) => return Ok(()),
+            Ok(Some(step)) => {
+                let ctx = DeliveryContext {
+                    config,
+                    session_id: Some(session_id),
+                    event: &step.event,
+                    payload: None,
+                    inline: false,
+                };
+                let names = step.backends.as_ref().unwrap_or(&config.notifications.backends);
+                // A failed rung shouldn't stop the ones after it
+                if let Err(e) = check_delivery(config, backends::dispatch_named(names, &step.data, &ctx)) {
+                    report_error(config, &e);
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use claude_notifier::types::Metadata;
     use serde_json::json;
 
     fn test_with_notification(name: &str, data: claude_notifier::types::NotificationData, config: &Config) {
@@ -101,34 +581,32 @@ mod tests {
 
         if config.testing.send_notifications {
             // Use a test session ID for testing
-            let test_session_id = Some(format!("test-session-{}", name));
-            if let Err(e) = terminal_notifier::send_notification(&data, config, test_session_id) {
-                eprintln!("Failed to send notification: {}", e);
-            } else {
-                println!("  ✓ Notification sent!");
-                std::thread::sleep(std::time::Duration::from_millis(config.testing.notification_delay));
+            let test_session_id = format!("test-session-{}", name);
+            let ctx = DeliveryContext {
+                config,
+                session_id: Some(&test_session_id),
+                event: "Test",
+                payload: None,
+                inline: false,
+            };
+            for report in backends::dispatch(&data, &ctx) {
+                match report.result {
+                    Ok(()) => println!("  ✓ Notification sent via {}!", report.backend),
+                    Err(e) => eprintln!("Failed to send notification via {}: {}", report.backend, e),
+                }
             }
+            std::thread::sleep(std::time::Duration::from_millis(config.testing.notification_delay));
         }
     }
 
     #[test]
     fn test_notification_event() {
         let config = load_config();
-        let payload = HookPayload {
-            event: "Notification".to_string(),
-            content: None,
-            metadata: Some(Metadata {
-                tool_name: Some("Bash".to_string()),
-                command: None,
-                description: None,
-                message: None,
-            }),
-            tool_name: None,
-            tool_input: None,
-            session_id: Some("test-session".to_string()),
-            transcript_path: None,
-            cwd: None,
-        };
+        let payload: HookPayload = serde_json::from_value(json!({
+            "event": "Notification",
+            "metadata": { "tool_name": "Bash" },
+            "session_id": "test-session"
+        })).unwrap();
 
         let result = process_hook_event(&payload, &config);
         assert_eq!(result.title, "Claude Needs Approval");
@@ -141,21 +619,11 @@ mod tests {
     #[test]
     fn test_notification_with_message() {
         let config = load_config();
-        let payload = HookPayload {
-            event: "Notification".to_string(),
-            content: None,
-            metadata: Some(Metadata {
-                tool_name: None,
-                command: None,
-                description: None,
-                message: Some("Custom approval message".to_string()),
-            }),
-            tool_name: None,
-            tool_input: None,
-            session_id: Some("test-session".to_string()),
-            transcript_path: None,
-            cwd: None,
-        };
+        let payload: HookPayload = serde_json::from_value(json!({
+            "event": "Notification",
+            "metadata": { "message": "Custom approval message" },
+            "session_id": "test-session"
+        })).unwrap();
 
         let result = process_hook_event(&payload, &config);
         assert_eq!(result.title, "Claude Needs Approval");
@@ -168,21 +636,11 @@ mod tests {
     #[test]
     fn test_pre_tool_use_bash() {
         let config = load_config();
-        let payload = HookPayload {
-            event: "PreToolUse".to_string(),
-            content: None,
-            metadata: Some(Metadata {
-                tool_name: Some("Bash".to_string()),
-                command: Some("ls -la".to_string()),
-                description: None,
-                message: None,
-            }),
-            tool_name: None,
-            tool_input: None,
-            session_id: Some("test-session".to_string()),
-            transcript_path: None,
-            cwd: None,
-        };
+        let payload: HookPayload = serde_json::from_value(json!({
+            "event": "PreToolUse",
+            "metadata": { "tool_name": "Bash", "command": "ls -la" },
+            "session_id": "test-session"
+        })).unwrap();
 
         let result = process_hook_event(&payload, &config);
         assert_eq!(result.title, "Claude Tool Use");
@@ -195,21 +653,11 @@ mod tests {
     #[test]
     fn test_pre_tool_use_other_tool() {
         let config = load_config();
-        let payload = HookPayload {
-            event: "PreToolUse".to_string(),
-            content: None,
-            metadata: Some(Metadata {
-                tool_name: Some("Read".to_string()),
-                command: None,
-                description: None,
-                message: None,
-            }),
-            tool_name: None,
-            tool_input: None,
-            session_id: Some("test-session".to_string()),
-            transcript_path: None,
-            cwd: None,
-        };
+        let payload: HookPayload = serde_json::from_value(json!({
+            "event": "PreToolUse",
+            "metadata": { "tool_name": "Read" },
+            "session_id": "test-session"
+        })).unwrap();
 
         let result = process_hook_event(&payload, &config);
         assert_eq!(result.title, "Claude Tool Use");
@@ -229,16 +677,11 @@ mod tests {
             }
         });
 
-        let payload = HookPayload {
-            event: "PreToolUse".to_string(),
-            content: Some(content),
-            metadata: None,
-            tool_name: None,
-            tool_input: None,
-            session_id: Some("test-session".to_string()),
-            transcript_path: None,
-            cwd: None,
-        };
+        let payload: HookPayload = serde_json::from_value(json!({
+            "event": "PreToolUse",
+            "content": content,
+            "session_id": "test-session"
+        })).unwrap();
 
         let result = process_hook_event(&payload, &config);
         assert_eq!(result.title, "Claude Tool Use");
@@ -251,21 +694,11 @@ mod tests {
     #[test]
     fn test_stop_event() {
         let config = load_config();
-        let payload = HookPayload {
-            event: "Stop".to_string(),
-            content: None,
-            metadata: Some(Metadata {
-                tool_name: None,
-                command: None,
-                description: Some("All tests passed successfully".to_string()),
-                message: None,
-            }),
-            tool_name: None,
-            tool_input: None,
-            session_id: Some("test-session".to_string()),
-            transcript_path: None,
-            cwd: None,
-        };
+        let payload: HookPayload = serde_json::from_value(json!({
+            "event": "Stop",
+            "metadata": { "description": "All tests passed successfully" },
+            "session_id": "test-session"
+        })).unwrap();
 
         let result = process_hook_event(&payload, &config);
         assert_eq!(result.title, "Claude Finished");
@@ -278,21 +711,11 @@ mod tests {
     #[test]
     fn test_stop_with_message() {
         let config = load_config();
-        let payload = HookPayload {
-            event: "Stop".to_string(),
-            content: None,
-            metadata: Some(Metadata {
-                tool_name: None,
-                command: None,
-                description: None,
-                message: Some("Build completed".to_string()),
-            }),
-            tool_name: None,
-            tool_input: None,
-            session_id: Some("test-session".to_string()),
-            transcript_path: None,
-            cwd: None,
-        };
+        let payload: HookPayload = serde_json::from_value(json!({
+            "event": "Stop",
+            "metadata": { "message": "Build completed" },
+            "session_id": "test-session"
+        })).unwrap();
 
         let result = process_hook_event(&payload, &config);
         assert_eq!(result.title, "Claude Finished");
@@ -305,16 +728,10 @@ mod tests {
     #[test]
     fn test_unknown_event() {
         let config = load_config();
-        let payload = HookPayload {
-            event: "UnknownEvent".to_string(),
-            content: None,
-            metadata: None,
-            tool_name: None,
-            tool_input: None,
-            session_id: Some("test-session".to_string()),
-            transcript_path: None,
-            cwd: None,
-        };
+        let payload: HookPayload = serde_json::from_value(json!({
+            "event": "UnknownEvent",
+            "session_id": "test-session"
+        })).unwrap();
 
         let result = process_hook_event(&payload, &config);
         assert_eq!(result.title, "Claude Event");
@@ -327,16 +744,10 @@ mod tests {
     #[test]
     fn test_empty_metadata() {
         let config = load_config();
-        let payload = HookPayload {
-            event: "Notification".to_string(),
-            content: None,
-            metadata: None,
-            tool_name: None,
-            tool_input: None,
-            session_id: Some("test-session".to_string()),
-            transcript_path: None,
-            cwd: None,
-        };
+        let payload: HookPayload = serde_json::from_value(json!({
+            "event": "Notification",
+            "session_id": "test-session"
+        })).unwrap();
 
         let result = process_hook_event(&payload, &config);
         assert_eq!(result.title, "Claude Needs Approval");
@@ -345,4 +756,228 @@ mod tests {
 
         test_with_notification("test_empty_metadata", result, &config);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_notification_top_level_message() {
+        let config = load_config();
+        let payload: HookPayload = serde_json::from_value(json!({
+            "hook_event_name": "Notification",
+            "session_id": "test-session",
+            "message": "Claude needs your permission to use Bash"
+        })).unwrap();
+
+        let result = process_hook_event(&payload, &config);
+        assert_eq!(result.title, "Claude Needs Approval");
+        assert_eq!(result.body, "Claude needs your permission to use Bash");
+
+        test_with_notification("test_notification_top_level_message", result, &config);
+    }
+
+    #[test]
+    fn test_post_tool_use_success() {
+        let config = load_config();
+        let payload: HookPayload = serde_json::from_value(json!({
+            "hook_event_name": "PostToolUse",
+            "session_id": "test-session",
+            "tool_name": "Bash",
+            "tool_input": { "command": "cargo test" },
+            "tool_response": { "stdout": "ok", "stderr": "", "interrupted": false }
+        })).unwrap();
+
+        let result = process_hook_event(&payload, &config);
+        assert_eq!(result.title, "Claude Tool Finished");
+        assert_eq!(result.body, "Finished: cargo test");
+        assert_eq!(result.sound, "Morse");
+
+        test_with_notification("test_post_tool_use_success", result, &config);
+    }
+
+    #[test]
+    fn test_post_tool_use_failure() {
+        let config = load_config();
+        let payload: HookPayload = serde_json::from_value(json!({
+            "hook_event_name": "PostToolUse",
+            "session_id": "test-session",
+            "tool_name": "Write",
+            "tool_input": { "file_path": "/etc/hosts" },
+            "tool_response": { "success": false, "error": "Permission denied" }
+        })).unwrap();
+
+        let result = process_hook_event(&payload, &config);
+        assert_eq!(result.title, "Claude Tool Failed");
+        assert_eq!(result.body, "Write failed: Permission denied");
+        assert_eq!(result.sound, "Basso");
+
+        test_with_notification("test_post_tool_use_failure", result, &config);
+    }
+
+    #[test]
+    fn test_session_lifecycle_events() {
+        let config = load_config();
+        let cases = [
+            (json!({ "hook_event_name": "SessionStart", "source": "resume" }),
+                "Claude Session Started", "Session started (resume)", "Blow"),
+            (json!({ "hook_event_name": "SessionEnd", "reason": "logout" }),
+                "Claude Session Ended", "Session ended (logout)", "Bottle"),
+            (json!({ "hook_event_name": "PreCompact", "trigger": "auto", "custom_instructions": "" }),
+                "Claude Compacting Context", "Compacting conversation (auto)", "Funk"),
+            (json!({ "hook_event_name": "SubagentStop", "stop_hook_active": false }),
+                "Claude Subagent Finished", "Subagent task completed", "Purr"),
+            (json!({ "hook_event_name": "UserPromptSubmit", "prompt": "Fix the build" }),
+                "Prompt Submitted", "Fix the build", "Tink"),
+        ];
+
+        for (value, title, body, sound) in cases {
+            let payload: HookPayload = serde_json::from_value(value).unwrap();
+            let result = process_hook_event(&payload, &config);
+            assert_eq!(result.title, title);
+            assert_eq!(result.body, body);
+            assert_eq!(result.sound, sound);
+        }
+    }
+
+    #[test]
+    fn test_disabled_event() {
+        let mut config = Config::default();
+        let payload: HookPayload = serde_json::from_value(json!({
+            "hook_event_name": "PreToolUse",
+            "tool_name": "Read"
+        })).unwrap();
+
+        assert!(should_notify(&payload, &config));
+        config.notifications.events.pre_tool_use = false;
+        assert!(!should_notify(&payload, &config));
+    }
+
+    #[test]
+    fn test_event_templates() {
+        let config: Config = toml::from_str(r#"
+            [notifications.templates.pre_tool_use]
+            title_template = "Claude in {cwd | basename}"
+            body_template = "{tool_name | upper}: {tool_input.command | truncate:8}"
+        "#).unwrap();
+        assert!(config.validate().is_ok());
+
+        let payload: HookPayload = serde_json::from_value(json!({
+            "hook_event_name": "PreToolUse",
+            "cwd": "/work/claude-notifier",
+            "tool_name": "Bash",
+            "tool_input": { "command": "cargo build --release" }
+        })).unwrap();
+
+        let result = process_hook_event(&payload, &config);
+        let result = apply_templates(result, &payload, None, &config);
+        assert_eq!(result.title, "Claude in claude-notifier");
+        assert_eq!(result.body, "BASH: cargo b…");
+        assert_eq!(result.sound, "Pop");
+    }
+
+    #[test]
+    fn test_invalid_template_rejected() {
+        let config: Config = toml::from_str(r#"
+            [notifications.templates.stop]
+            body_template = "{cwd | reverse}"
+        "#).unwrap();
+        assert!(config.validate().is_err());
+
+        let config: Config = toml::from_str(r#"
+            [notifications.templates.stopp]
+            body_template = "{cwd}"
+        "#).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_blocking_exit_code_rejected() {
+        let config: Config = toml::from_str("[hook]\nexit_code_on_error = 2").unwrap();
+        assert!(config.validate().is_err());
+
+        let config: Config = toml::from_str("[hook]\nexit_code_on_error = 1").unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_invalid_section_dropped_alone() {
+        let mut config: Config = toml::from_str(r##"
+            [notifications]
+            backends = ["slack"]
+            [schedule]
+            timezone = "Mars/Olympus_Mons"
+            [[schedule.quiet]]
+            start = "22:00"
+            end = "07:00"
+            [backends.slack]
+            colors = { stop = "green" }
+            [[backends.slack.channels]]
+            url = "https://hooks.slack.com/services/x"
+            [[rules]]
+            event = "Stop"
+            action = "suppress"
+        "##).unwrap();
+
+        let errors = config.drop_invalid_sections();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].starts_with("schedule: "));
+        assert!(config.schedule.quiet.is_empty());
+        assert!(config.backends.slack.channels.is_empty());
+        // Everything that was valid is kept
+        assert_eq!(config.notifications.backends, vec!["slack"]);
+        assert_eq!(config.rules.len(), 1);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_delivery_failure_is_an_error() {
+        let config = Config::default();
+        let failed = |backend: &str| DeliveryReport { backend: backend.to_string(), result: Err("down".to_string()) };
+        let ok = DeliveryReport { backend: "dbus".to_string(), result: Ok(()) };
+
+        assert!(check_delivery(&config, vec![failed("dbus"), ok]).is_ok());
+        let err = check_delivery(&config, vec![failed("dbus"), failed("terminal-notifier")]).unwrap_err();
+        assert_eq!(err.to_string(), "All notification backends failed: dbus: down; terminal-notifier: down");
+    }
+
+    #[test]
+    fn test_decided_job_only_records_activity() {
+        let dir = std::env::temp_dir().join(format!("claude-notifier-decided-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let store = SessionStore::with_base_dir(dir.clone());
+        // Sending would fail, as the webhook has no URL
+        let config: Config = toml::from_str("[notifications]\nbackends = [\"webhook\"]\n[notifications.events]\npre_tool_use = true").unwrap();
+        let buffer = r#"{"hook_event_name":"PreToolUse","session_id":"s1","cwd":"/work","tool_name":"Bash","tool_input":{"command":"ls"}}"#;
+        let payload: HookPayload = serde_json::from_str(buffer).unwrap();
+
+        let job = Job { decided: true, ..Job::new(buffer.to_string()) };
+        assert!(deliver(&config, &store, &payload, &job, false).is_ok());
+        assert_eq!(store.get_session("s1").unwrap().cwd.as_deref(), Some("/work"));
+
+        assert!(deliver(&config, &store, &payload, &Job::new(buffer.to_string()), false).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_summaries_routed_by_rules() {
+        let config: Config = toml::from_str(r#"
+            [[rules]]
+            event = "PreToolUse"
+            sound = "Purr"
+            backends = ["ntfy"]
+
+            [[rules]]
+            event = "Stop"
+            action = "suppress"
+        "#).unwrap();
+        let summary = |event: &str| HookPayload {
+            session_id: Some("s1".to_string()),
+            transcript_path: None,
+            cwd: None,
+            event: HookEvent::Unknown(serde_json::json!({ "hook_event_name": event })),
+        };
+        let data = || NotificationData { title: "Claude Activity".to_string(), body: "Claude ran 2 more tools".to_string(), sound: "Pop".to_string() };
+
+        let (routed, backend_names) = route(&config, &summary("PreToolUse"), data()).unwrap();
+        assert_eq!(routed.sound, "Purr");
+        assert_eq!(backend_names, vec!["ntfy"]);
+        assert!(route(&config, &summary("Stop"), data()).is_none());
+    }
+}
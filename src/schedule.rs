@@ -0,0 +1,298 @@
+use std::path::{Component, Path};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Quiet hours, configured under `[schedule]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduleConfig {
+    /// IANA timezone such as `Europe/Berlin`; the system timezone if unset
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// What happens to notifications during quiet hours
+    #[serde(default)]
+    pub mode: QuietMode,
+    /// Backends used during quiet hours when `mode = "backends"`
+    #[serde(default)]
+    pub backends: Vec<String>,
+    /// Events delivered normally even during quiet hours
+    #[serde(default = "default_breakthrough_events")]
+    pub breakthrough_events: Vec<String>,
+    #[serde(default)]
+    pub quiet: Vec<QuietWindow>,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            timezone: None,
+            mode: QuietMode::default(),
+            backends: Vec::new(),
+            breakthrough_events: default_breakthrough_events(),
+            quiet: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuietMode {
+    /// Drop the notification entirely
+    Suppress,
+    /// Deliver without a sound
+    #[default]
+    Silent,
+    /// Deliver only through `schedule.backends`
+    Backends,
+}
+
+/// A recurring quiet window. Windows where `end` is before `start` run past
+/// midnight and belong to the day they start on.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuietWindow {
+    /// Days the window starts on (`mon` .. `sun`); every day if empty
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// `HH:MM`, inclusive
+    pub start: String,
+    /// `HH:MM`, exclusive
+    pub end: String,
+}
+
+/// What to do with a notification given the schedule
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScheduleDecision {
+    Deliver,
+    Suppress,
+    Silent,
+    Route(Vec<String>),
+}
+
+/// A point in the week: day 0 is Monday, minutes since midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeekTime {
+    pub weekday: u8,
+    pub minutes: u16,
+}
+
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+const FULL_DAY_NAMES: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+/// Where `date` looks up the zones `timezone` names
+const ZONEINFO_DIR: &str = "/usr/share/zoneinfo";
+
+impl ScheduleConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(ref timezone) = self.timezone {
+            validate_timezone(timezone)?;
+        }
+        for window in &self.quiet {
+            parse_time(&window.start)?;
+            parse_time(&window.end)?;
+            for day in &window.days {
+                parse_day(day)?;
+            }
+        }
+
+        if self.mode == QuietMode::Backends && self.backends.is_empty() {
+            return Err("schedule.mode = \"backends\" needs at least one entry in schedule.backends".to_string());
+        }
+
+        Ok(())
+    }
+
+    pub fn is_quiet(&self, at: WeekTime) -> bool {
+        self.quiet.iter().any(|window| window.contains(at))
+    }
+
+    /// Decide how to deliver `event` at the given time
+    pub fn decide(&self, event: &str, at: WeekTime) -> ScheduleDecision {
+        if !self.is_quiet(at) || self.breakthrough_events.iter().any(|e| e == event) {
+            return ScheduleDecision::Deliver;
+        }
+
+        match self.mode {
+            QuietMode::Suppress => ScheduleDecision::Suppress,
+            QuietMode::Silent => ScheduleDecision::Silent,
+            QuietMode::Backends => ScheduleDecision::Route(self.backends.clone()),
+        }
+    }
+
+    /// Decide how to deliver `event` right now
+    pub fn decide_now(&self, event: &str) -> ScheduleDecision {
+        if self.quiet.is_empty() {
+            return ScheduleDecision::Deliver;
+        }
+        self.decide(event, current_time(self.timezone.as_deref()))
+    }
+}
+
+impl QuietWindow {
+    pub fn contains(&self, at: WeekTime) -> bool {
+        let (Ok(start), Ok(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        let starts_on = |weekday: u8| {
+            self.days.is_empty() || self.days.iter().any(|d| parse_day(d) == Ok(weekday))
+        };
+
+        if start <= end {
+            starts_on(at.weekday) && at.minutes >= start && at.minutes < end
+        } else {
+            let yesterday = (at.weekday + 6) % 7;
+            (starts_on(at.weekday) && at.minutes >= start) || (starts_on(yesterday) && at.minutes < end)
+        }
+    }
+}
+
+fn parse_time(value: &str) -> Result<u16, String> {
+    let invalid = || format!("Invalid time '{}', expected HH:MM", value);
+    let (hours, minutes) = value.split_once(':').ok_or_else(invalid)?;
+    let hours: u16 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u16 = minutes.parse().map_err(|_| invalid())?;
+
+    if hours > 24 || minutes > 59 || (hours == 24 && minutes != 0) {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+fn parse_day(value: &str) -> Result<u8, String> {
+    let lower = value.to_lowercase();
+    DAY_NAMES.iter()
+        .zip(FULL_DAY_NAMES)
+        .position(|(short, full)| lower == *short || lower == full)
+        .map(|index| index as u8)
+        .ok_or_else(|| format!("Invalid day '{}', expected mon, tue, wed, thu, fri, sat or sun", value))
+}
+
+/// An unknown zone makes `date` fall back to UTC without a word, so check
+/// that the zone exists, and that it names one rather than any file
+fn validate_timezone(timezone: &str) -> Result<(), String> {
+    validate_timezone_in(Path::new(ZONEINFO_DIR), timezone)
+}
+
+fn validate_timezone_in(zoneinfo: &Path, timezone: &str) -> Result<(), String> {
+    let path = Path::new(timezone);
+    let relative = !timezone.is_empty() && path.components().all(|c| matches!(c, Component::Normal(_)));
+    if !relative || !zoneinfo.join(path).is_file() {
+        return Err(format!("Unknown timezone '{}', expected a name such as Europe/Berlin from {}", timezone, zoneinfo.display()));
+    }
+    Ok(())
+}
+
+/// The current weekday and time in `timezone`, asking `date` so the system tz database is used
+pub fn current_time(timezone: Option<&str>) -> WeekTime {
+    let mut cmd = Command::new("date");
+    cmd.arg("+%u %H:%M");
+    if let Some(tz) = timezone {
+        cmd.env("TZ", tz);
+    }
+
+    let parsed = cmd.output().ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| parse_date_output(&String::from_utf8_lossy(&output.stdout)));
+
+    parsed.unwrap_or_else(utc_now)
+}
+
+fn parse_date_output(output: &str) -> Option<WeekTime> {
+    let (day, time) = output.trim().split_once(' ')?;
+    let day: u8 = day.parse().ok()?;
+    if !(1..=7).contains(&day) {
+        return None;
+    }
+    Some(WeekTime { weekday: day - 1, minutes: parse_time(time).ok()? })
+}
+
+fn utc_now() -> WeekTime {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    // 1970-01-01 was a Thursday
+    WeekTime {
+        weekday: ((secs / 86400 + 3) % 7) as u8,
+        minutes: ((secs % 86400) / 60) as u16,
+    }
+}
+
+fn default_breakthrough_events() -> Vec<String> { vec!["Notification".to_string()] }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(weekday: u8, time: &str) -> WeekTime {
+        WeekTime { weekday, minutes: parse_time(time).unwrap() }
+    }
+
+    fn schedule() -> ScheduleConfig {
+        toml::from_str(r#"
+            mode = "suppress"
+            [[quiet]]
+            days = ["mon", "tue", "wed", "thu", "fri"]
+            start = "22:00"
+            end = "07:00"
+            [[quiet]]
+            days = ["sat", "sun"]
+            start = "00:00"
+            end = "24:00"
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_overnight_window() {
+        let schedule = schedule();
+        assert!(schedule.is_quiet(at(0, "23:30")));   // Monday night
+        assert!(schedule.is_quiet(at(1, "06:59")));   // Tuesday morning
+        assert!(!schedule.is_quiet(at(1, "07:00")));
+        assert!(!schedule.is_quiet(at(0, "06:00")));  // Sunday night isn't a weekday window
+        assert!(schedule.is_quiet(at(5, "12:00")));   // Saturday
+    }
+
+    #[test]
+    fn test_breakthrough_events() {
+        let schedule = schedule();
+        assert_eq!(schedule.decide("Stop", at(2, "23:00")), ScheduleDecision::Suppress);
+        assert_eq!(schedule.decide("Notification", at(2, "23:00")), ScheduleDecision::Deliver);
+        assert_eq!(schedule.decide("Stop", at(2, "12:00")), ScheduleDecision::Deliver);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(schedule().validate().is_ok());
+
+        let mut bad = schedule();
+        bad.quiet[0].start = "25:00".to_string();
+        assert!(bad.validate().is_err());
+
+        let mut bad = schedule();
+        bad.quiet[0].days = vec!["someday".to_string()];
+        assert!(bad.validate().is_err());
+        assert_eq!(parse_day("Saturday"), Ok(5));
+        assert!(parse_day("month").is_err());
+
+        // A zoneinfo directory of the test's own, as the host may have none
+        let zoneinfo = std::env::temp_dir().join(format!("claude-notifier-zoneinfo-{}", std::process::id()));
+        std::fs::create_dir_all(zoneinfo.join("Europe")).unwrap();
+        std::fs::write(zoneinfo.join("Europe/Berlin"), "TZif").unwrap();
+        assert!(validate_timezone_in(&zoneinfo, "Europe/Berlin").is_ok());
+        assert!(validate_timezone_in(&zoneinfo, "Europe").is_err());
+        assert!(validate_timezone_in(&zoneinfo, "Mars/Olympus_Mons").is_err());
+        assert!(validate_timezone_in(&zoneinfo, "../../etc/passwd").is_err());
+        std::fs::remove_dir_all(&zoneinfo).ok();
+
+        let mut zoned = schedule();
+        zoned.timezone = Some("Mars/Olympus_Mons".to_string());
+        assert!(zoned.validate().is_err());
+
+        let mut bad = schedule();
+        bad.mode = QuietMode::Backends;
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_date_output() {
+        assert_eq!(parse_date_output("3 14:05\n"), Some(at(2, "14:05")));
+        assert_eq!(parse_date_output("garbage"), None);
+    }
+}
@@ -2,6 +2,8 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use serde::{Deserialize, Serialize};
+use crate::lock::FileLock;
+use crate::private_dir;
 use crate::terminal_detector::TerminalInfo;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +13,78 @@ pub struct SessionInfo {
     pub created_at: u64,
     pub cwd: Option<String>,
     pub transcript_path: Option<String>,
+    /// Notifications sent recently, used for debouncing
+    #[serde(default)]
+    pub recent: Vec<SentNotification>,
+    /// Notifications held back during the current bursts, one per debounce key
+    #[serde(default)]
+    pub pending_summaries: Vec<PendingSummary>,
+    /// Permission decisions made by the policy, oldest first
+    #[serde(default)]
+    pub decisions: Vec<DecisionRecord>,
+    /// An approval request that escalates until the session moves on
+    #[serde(default)]
+    pub pending_escalation: Option<PendingEscalation>,
+    /// Receipt of a Pushover emergency message that repeats until cancelled
+    #[serde(default)]
+    pub pushover_receipt: Option<String>,
+    /// When the hook behind the last MQTT state published for the session ran
+    #[serde(default)]
+    pub mqtt_state_at_ms: u64,
+    /// Events since the last digest, oldest first
+    #[serde(default)]
+    pub history: Vec<HistoryEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentNotification {
+    pub key: String,
+    pub sent_at_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingSummary {
+    /// The debounce key of the notifications it stands for
+    #[serde(default)]
+    pub key: String,
+    pub event: String,
+    pub count: u32,
+    #[serde(default)]
+    pub tools: Vec<String>,
+    pub last_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingEscalation {
+    /// Identifies this escalation to its watcher, which stops if it's replaced
+    pub armed_at_ms: u64,
+    /// Index of the next step to send
+    pub next_step: usize,
+    pub event: String,
+    pub title: String,
+    pub body: String,
+    pub sound: String,
+}
+
+/// A hook event as it was notified, kept for digests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub at_ms: u64,
+    pub event: String,
+    pub title: String,
+    pub body: String,
+}
+
+/// An audit entry for a permission decision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionRecord {
+    pub at_ms: u64,
+    pub tool: String,
+    /// The command, path or pattern the tool was called with
+    pub input: String,
+    /// `allow`, `deny` or `ask`
+    pub decision: String,
+    pub reason: String,
 }
 
 pub struct SessionStore {
@@ -19,39 +93,83 @@ pub struct SessionStore {
 
 impl SessionStore {
     pub fn new() -> Self {
-        let base_dir = std::env::temp_dir().join("claude-notifier-sessions");
-        fs::create_dir_all(&base_dir).ok();
-        
+        let store = Self::open();
+
         // Clean up old sessions on startup
-        Self::cleanup_old_sessions(&base_dir);
-        
+        store.cleanup();
+
+        store
+    }
+
+    /// The user's store, without the cleanup pass `new` does. Sessions hold
+    /// prompts and delivery state, so they're kept where only the user can
+    /// read them.
+    pub fn open() -> Self {
+        Self::with_base_dir(private_dir::default_dir("sessions"))
+    }
+
+    /// Remove sessions that haven't been touched for a day
+    pub fn cleanup(&self) {
+        Self::cleanup_old_sessions(&self.base_dir);
+    }
+
+    /// A store rooted somewhere other than the default directory
+    pub fn with_base_dir(base_dir: PathBuf) -> Self {
+        private_dir::create(&base_dir).ok();
         SessionStore { base_dir }
     }
     
     pub fn store_session(&self, session_id: &str, terminal_info: TerminalInfo, cwd: Option<String>, transcript_path: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+        let _lock = self.lock(session_id)?;
+
+        // Keep the notification history across hook invocations
+        let previous = self.get_session(session_id);
+
         let session_info = SessionInfo {
             session_id: session_id.to_string(),
             terminal_info,
             created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
             cwd,
             transcript_path,
+            recent: previous.as_ref().map(|p| p.recent.clone()).unwrap_or_default(),
+            pending_summaries: previous.as_ref().map(|p| p.pending_summaries.clone()).unwrap_or_default(),
+            decisions: previous.as_ref().map(|p| p.decisions.clone()).unwrap_or_default(),
+            pending_escalation: previous.as_ref().and_then(|p| p.pending_escalation.clone()),
+            pushover_receipt: previous.as_ref().and_then(|p| p.pushover_receipt.clone()),
+            mqtt_state_at_ms: previous.as_ref().map(|p| p.mqtt_state_at_ms).unwrap_or_default(),
+            history: previous.map(|p| p.history).unwrap_or_default(),
         };
         
-        let file_path = self.session_file_path(session_id);
-        let json = serde_json::to_string_pretty(&session_info)?;
-        fs::write(file_path, json)?;
+        self.write_session(&session_info)
+    }
+
+    /// Load a session, let `update` modify it and write it back. Other
+    /// processes updating the same session wait until this one is done.
+    pub fn update_session<R>(&self, session_id: &str, update: impl FnOnce(&mut SessionInfo) -> R) -> Option<R> {
+        let _lock = self.lock(session_id).ok()?;
+        let mut session = self.get_session(session_id)?;
+        let result = update(&mut session);
+        self.write_session(&session).ok()?;
+        Some(result)
+    }
+
+    fn lock(&self, session_id: &str) -> std::io::Result<FileLock> {
+        private_dir::create(&self.base_dir)?;
+        FileLock::acquire(&self.session_file_path(session_id).with_extension("lock"))
+    }
+
+    /// Replace the session file in one step, so readers never see half of it
+    fn write_session(&self, session_info: &SessionInfo) -> Result<(), Box<dyn std::error::Error>> {
+        let file_path = self.session_file_path(&session_info.session_id);
+        let json = serde_json::to_string_pretty(session_info)?;
+        private_dir::write(&file_path, json.as_bytes())?;
         
         Ok(())
     }
     
     pub fn get_session(&self, session_id: &str) -> Option<SessionInfo> {
-        let file_path = self.session_file_path(session_id);
-        if !file_path.exists() {
-            return None;
-        }
-        
-        let contents = fs::read_to_string(file_path).ok()?;
-        serde_json::from_str(&contents).ok()
+        let contents = private_dir::read(&self.session_file_path(session_id)).ok()?;
+        serde_json::from_slice(&contents).ok()
     }
     
     fn session_file_path(&self, session_id: &str) -> PathBuf {
@@ -105,4 +223,50 @@ impl Default for SessionStore {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_concurrent_updates_are_not_lost() {
+        let dir = std::env::temp_dir().join(format!("claude-notifier-sessions-test-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        SessionStore::with_base_dir(dir.clone()).store_session("s1", TerminalInfo::default(), None, None).unwrap();
+
+        let writers: Vec<_> = (0..8).map(|writer| {
+            let dir = dir.clone();
+            std::thread::spawn(move || {
+                let store = SessionStore::with_base_dir(dir);
+                for at_ms in 0..25 {
+                    let entry = HistoryEntry { at_ms, event: writer.to_string(), title: String::new(), body: String::new() };
+                    store.update_session("s1", |session| session.history.push(entry)).unwrap();
+                }
+            })
+        }).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let store = SessionStore::with_base_dir(dir.clone());
+        assert_eq!(store.get_session("s1").unwrap().history.len(), 200);
+        assert_eq!(store.list_sessions(), vec!["s1"]);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_linked_sessions_are_not_read() {
+        let dir = std::env::temp_dir().join(format!("claude-notifier-sessions-link-test-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        let store = SessionStore::with_base_dir(dir.clone());
+        store.store_session("real", TerminalInfo::default(), None, None).unwrap();
+        std::os::unix::fs::symlink(dir.join("real.json"), dir.join("linked.json")).unwrap();
+
+        assert!(store.get_session("real").is_some());
+        assert!(store.get_session("linked").is_none());
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,353 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use crate::terminal_detector::TerminalInfo;
+use crate::types::{Config, HookPayload, NotificationData};
+
+/// Placeholders that are always available, regardless of the event
+pub const CONTEXT_FIELDS: &[&str] = &[
+    "event", "title", "body", "session_id", "cwd", "project_dir",
+    "transcript_path", "terminal_app", "timestamp", "unix_time",
+];
+
+/// Top-level hook payload fields that can be used directly as placeholders
+pub const PAYLOAD_FIELDS: &[&str] = &[
+    "tool_name", "message", "prompt", "trigger", "custom_instructions",
+    "source", "reason", "stop_hook_active",
+];
+
+/// Payload objects whose nested fields can be reached with a dotted path
+pub const NESTED_FIELDS: &[&str] = &["tool_input", "tool_response"];
+
+/// A parsed `{placeholder | filter}` template
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Placeholder { path: String, filters: Vec<Filter> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Truncate(usize),
+    Upper,
+    Lower,
+    Trim,
+    Basename,
+    Default(String),
+}
+
+impl Template {
+    /// Parse a template string. `{{` and `}}` produce literal braces.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') => return Err(format!("Nested '{{' in template: {}", source)),
+                            Some(c) => inner.push(c),
+                            None => return Err(format!("Unclosed '{{' in template: {}", source)),
+                        }
+                    }
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(parse_placeholder(&inner)?);
+                }
+                '}' => return Err(format!("Unmatched '}}' in template: {}", source)),
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Template { segments })
+    }
+
+    /// Check that every placeholder refers to a field that can exist
+    pub fn validate_fields(&self) -> Result<(), String> {
+        for segment in &self.segments {
+            if let Segment::Placeholder { path, .. } = segment {
+                if !is_known_field(path) {
+                    return Err(format!("Unknown template placeholder: {{{}}}", path));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Render the template, looking placeholders up with `resolve`
+    pub fn render(&self, resolve: impl Fn(&str) -> Option<String>) -> String {
+        let mut output = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => output.push_str(text),
+                Segment::Placeholder { path, filters } => {
+                    let mut value = resolve(path);
+                    for filter in filters {
+                        value = filter.apply(value);
+                    }
+                    output.push_str(value.as_deref().unwrap_or(""));
+                }
+            }
+        }
+
+        output
+    }
+}
+
+fn parse_placeholder(inner: &str) -> Result<Segment, String> {
+    let mut parts = inner.split('|').map(str::trim);
+    let path = parts.next().unwrap_or_default();
+
+    if path.is_empty() {
+        return Err("Empty template placeholder".to_string());
+    }
+
+    let filters = parts.map(parse_filter).collect::<Result<Vec<_>, _>>()?;
+    Ok(Segment::Placeholder { path: path.to_string(), filters })
+}
+
+fn parse_filter(spec: &str) -> Result<Filter, String> {
+    let (name, arg) = match spec.split_once(':') {
+        Some((name, arg)) => (name.trim(), Some(arg.trim())),
+        None => (spec, None),
+    };
+
+    match (name, arg) {
+        ("truncate", Some(len)) => len.parse()
+            .map(Filter::Truncate)
+            .map_err(|_| format!("Invalid truncate length: {}", len)),
+        ("truncate", None) => Err("The truncate filter needs a length, e.g. truncate:80".to_string()),
+        ("upper", None) => Ok(Filter::Upper),
+        ("lower", None) => Ok(Filter::Lower),
+        ("trim", None) => Ok(Filter::Trim),
+        ("basename", None) => Ok(Filter::Basename),
+        ("default", Some(text)) => Ok(Filter::Default(text.to_string())),
+        ("upper" | "lower" | "trim" | "basename", Some(_)) => {
+            Err(format!("The {} filter takes no argument", name))
+        }
+        ("default", None) => Err("The default filter needs a value, e.g. default:none".to_string()),
+        _ => Err(format!("Unknown template filter: {}", name)),
+    }
+}
+
+impl Filter {
+    fn apply(&self, value: Option<String>) -> Option<String> {
+        match self {
+            Filter::Default(text) => match value {
+                Some(v) if !v.is_empty() => Some(v),
+                _ => Some(text.clone()),
+            },
+            Filter::Truncate(max) => value.map(|v| truncate(&v, *max)),
+            Filter::Upper => value.map(|v| v.to_uppercase()),
+            Filter::Lower => value.map(|v| v.to_lowercase()),
+            Filter::Trim => value.map(|v| v.trim().to_string()),
+            Filter::Basename => value.map(|v| basename(&v)),
+        }
+    }
+}
+
+fn is_known_field(path: &str) -> bool {
+    if CONTEXT_FIELDS.contains(&path) || PAYLOAD_FIELDS.contains(&path) {
+        return true;
+    }
+
+    match path.split_once('.') {
+        Some((root, rest)) => NESTED_FIELDS.contains(&root) && !rest.is_empty(),
+        None => NESTED_FIELDS.contains(&path),
+    }
+}
+
+/// Shorten `text` to at most `max_chars` characters, marking the cut with an
+/// ellipsis that counts towards the limit
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else if max_chars == 0 {
+        String::new()
+    } else {
+        let truncated: String = text.chars().take(max_chars - 1).collect();
+        format!("{}…", truncated.trim_end())
+    }
+}
+
+fn basename(path: &str) -> String {
+    Path::new(path.trim_end_matches('/'))
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Everything a template can draw from when it's rendered
+pub struct TemplateContext<'a> {
+    pub payload: &'a HookPayload,
+    pub terminal: Option<&'a TerminalInfo>,
+    pub title: &'a str,
+    pub body: &'a str,
+}
+
+impl TemplateContext<'_> {
+    pub fn resolve(&self, path: &str) -> Option<String> {
+        match path {
+            "event" => Some(self.payload.event.name().to_string()),
+            "title" => Some(self.title.to_string()),
+            "body" => Some(self.body.to_string()),
+            "session_id" => self.payload.session_id.clone(),
+            "cwd" => self.payload.cwd.clone()
+                .or_else(|| self.terminal.and_then(|t| t.cwd.clone())),
+            "transcript_path" => self.payload.transcript_path.clone(),
+            "project_dir" => self.terminal.and_then(|t| t.project_dir.clone()),
+            "terminal_app" => self.terminal.and_then(|t| t.terminal_app.clone()),
+            "timestamp" => Some(format_timestamp(unix_time())),
+            "unix_time" => Some(unix_time().to_string()),
+            _ => {
+                let event = serde_json::to_value(&self.payload.event).ok()?;
+                lookup(&event, path)
+            }
+        }
+    }
+}
+
+/// Follow a dotted path into a JSON value and render the result as text
+fn lookup(value: &Value, path: &str) -> Option<String> {
+    let mut current = value;
+    for key in path.split('.') {
+        current = match current {
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+            _ => current.get(key)?,
+        };
+    }
+
+    match current {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// Format seconds since the epoch as an RFC 3339 UTC timestamp
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let time = secs % 86400;
+
+    // Civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, time / 3600, time % 3600 / 60, time % 60
+    )
+}
+
+/// Replace the title and body with the configured templates for this event, if any
+pub fn apply_templates(
+    data: NotificationData,
+    payload: &HookPayload,
+    terminal: Option<&TerminalInfo>,
+    config: &Config,
+) -> NotificationData {
+    let Some(templates) = config.notifications.templates.get(&payload.event.config_key()) else {
+        return data;
+    };
+
+    let ctx = TemplateContext { payload, terminal, title: &data.title, body: &data.body };
+    let render = |source: &Option<String>| {
+        source.as_deref()
+            .and_then(|source| Template::parse(source).ok())
+            .map(|template| template.render(|path| ctx.resolve(path)))
+    };
+
+    let title = render(&templates.title_template);
+    let body = render(&templates.body_template);
+
+    NotificationData {
+        title: title.unwrap_or(data.title),
+        body: body.unwrap_or(data.body),
+        sound: data.sound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn payload() -> HookPayload {
+        serde_json::from_value(json!({
+            "hook_event_name": "PreToolUse",
+            "session_id": "abc123",
+            "cwd": "/home/me/projects/notifier/",
+            "tool_name": "Bash",
+            "tool_input": { "command": "cargo test --workspace", "args": ["a", "b"] }
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_render_placeholders_and_filters() {
+        let payload = payload();
+        let ctx = TemplateContext { payload: &payload, terminal: None, title: "Claude Tool Use", body: "" };
+        let template = Template::parse(
+            "{cwd | basename | upper}: {tool_input.command | truncate:10} {tool_input.args.1} {{ok}} {prompt | default:-}"
+        ).unwrap();
+
+        assert_eq!(template.render(|p| ctx.resolve(p)), "NOTIFIER: cargo tes… b {ok} -");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Template::parse("{unclosed").is_err());
+        assert!(Template::parse("stray }").is_err());
+        assert!(Template::parse("{cwd | shout}").is_err());
+        assert!(Template::parse("{cwd | truncate:many}").is_err());
+        assert!(Template::parse("{}").is_err());
+    }
+
+    #[test]
+    fn test_validate_fields() {
+        assert!(Template::parse("{tool_input.file_path} {session_id}").unwrap().validate_fields().is_ok());
+        assert!(Template::parse("{sesion_id}").unwrap().validate_fields().is_err());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+}
@@ -1,8 +1,59 @@
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::fs;
+use std::path::Path;
 use std::process::Command;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Environment variables terminal detection looks at
+pub const DETECTION_ENV_VARS: &[&str] = &[
+    "CLAUDE_PROJECT_DIR",
+    "GIT_ASKPASS",
+    "CURSOR_TRACE_ID",
+    "TERM_PROGRAM",
+    "VSCODE_INJECTION",
+    "VSCODE_PID",
+    "TERMINAL_EMULATOR",
+    "ALACRITTY_SOCKET",
+    "WEZTERM_PANE",
+    "KITTY_WINDOW_ID",
+    "WINDOWID",
+    "TMUX_PANE",
+    // Where the focus checks have to look
+    "TMUX",
+    "SWAYSOCK",
+    "I3SOCK",
+    "DISPLAY",
+    "XAUTHORITY",
+];
+
+/// What detection needs to know about the hook process, captured cheaply so
+/// the expensive part can run elsewhere (a detached child or the daemon)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DetectionContext {
+    pub parent_pid: Option<u32>,
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+impl DetectionContext {
+    pub fn current() -> Self {
+        DetectionContext {
+            parent_pid: Some(std::os::unix::process::parent_id()),
+            cwd: env::current_dir().ok().and_then(|p| p.to_str().map(String::from)),
+            env: DETECTION_ENV_VARS.iter()
+                .filter_map(|name| env::var(name).ok().map(|value| (name.to_string(), value)))
+                .collect(),
+        }
+    }
+
+    pub fn var(&self, name: &str) -> Option<String> {
+        self.env.get(name).cloned()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TerminalInfo {
     pub terminal_app: Option<String>,
     pub window_id: Option<String>,
@@ -11,10 +62,24 @@ pub struct TerminalInfo {
     pub parent_pid: Option<u32>,
     pub claude_pid: Option<u32>,
     pub cwd: Option<String>,
+    /// `$TMUX_PANE` when running inside tmux
+    #[serde(default)]
+    pub tmux_pane: Option<String>,
+    /// Controlling terminal of the process that ran the hook, e.g. `/dev/pts/3`
+    #[serde(default)]
+    pub tty: Option<String>,
+    /// Our parent, grandparent, ... used to find the terminal's window by pid
+    #[serde(default)]
+    pub ancestor_pids: Vec<u32>,
 }
 
 impl TerminalInfo {
     pub fn detect() -> Self {
+        Self::detect_in(&DetectionContext::current())
+    }
+
+    /// Detect on behalf of the hook process described by `ctx`
+    pub fn detect_in(ctx: &DetectionContext) -> Self {
         let mut info = TerminalInfo {
             terminal_app: None,
             window_id: None,
@@ -23,19 +88,22 @@ impl TerminalInfo {
             parent_pid: None,
             claude_pid: None,
             cwd: None,
+            tmux_pane: None,
+            tty: None,
+            ancestor_pids: Vec::new(),
         };
 
         // Get CLAUDE_PROJECT_DIR if available
-        info.project_dir = env::var("CLAUDE_PROJECT_DIR").ok();
+        info.project_dir = ctx.var("CLAUDE_PROJECT_DIR");
         
         // Get current working directory
-        info.cwd = env::current_dir().ok().and_then(|p| p.to_str().map(String::from));
+        info.cwd = ctx.cwd.clone();
         
         // Detect terminal app from environment variables
-        info.terminal_app = detect_terminal_from_env();
+        info.terminal_app = detect_terminal_from_env(ctx);
         
         // Get parent process info
-        if let Some(ppid) = get_parent_pid() {
+        if let Some(ppid) = ctx.parent_pid {
             info.parent_pid = Some(ppid);
             
             // Try to get more info about the parent process
@@ -47,31 +115,32 @@ impl TerminalInfo {
             }
         }
         
-        // Try to get window ID on macOS
-        #[cfg(target_os = "macos")]
-        {
-            info.window_id = get_macos_window_id();
-        }
+        // Try to get window ID (X11 terminals export it as WINDOWID)
+        info.window_id = get_macos_window_id().or_else(|| ctx.var("WINDOWID"));
+
+        info.tmux_pane = ctx.var("TMUX_PANE");
+        info.tty = ctx.parent_pid.and_then(get_tty_of);
+        info.ancestor_pids = ctx.parent_pid.map(get_ancestor_pids).unwrap_or_default();
         
         info
     }
 }
 
-fn detect_terminal_from_env() -> Option<String> {
+fn detect_terminal_from_env(ctx: &DetectionContext) -> Option<String> {
     // Check for Cursor first (it also sets TERM_PROGRAM=vscode)
-    if let Ok(git_askpass) = env::var("GIT_ASKPASS") {
+    if let Some(git_askpass) = ctx.var("GIT_ASKPASS") {
         if git_askpass.contains("Cursor.app") {
             return Some("Cursor".to_string());
         }
     }
     
     // Check for Cursor via other environment variables
-    if env::var("CURSOR_TRACE_ID").is_ok() {
+    if ctx.var("CURSOR_TRACE_ID").is_some() {
         return Some("Cursor".to_string());
     }
     
     // Check common terminal environment variables
-    if let Ok(term_program) = env::var("TERM_PROGRAM") {
+    if let Some(term_program) = ctx.var("TERM_PROGRAM") {
         return Some(match term_program.as_str() {
             "iTerm.app" => "iTerm2".to_string(),
             "Apple_Terminal" => "Terminal".to_string(),
@@ -83,46 +152,107 @@ fn detect_terminal_from_env() -> Option<String> {
     }
     
     // Check for VS Code
-    if env::var("VSCODE_INJECTION").is_ok() || env::var("VSCODE_PID").is_ok() {
+    if ctx.var("VSCODE_INJECTION").is_some() || ctx.var("VSCODE_PID").is_some() {
         return Some("VSCode".to_string());
     }
     
     // Check for other terminals
-    if let Ok(terminal) = env::var("TERMINAL_EMULATOR") {
+    if let Some(terminal) = ctx.var("TERMINAL_EMULATOR") {
         return Some(terminal);
     }
     
     // Check for Alacritty
-    if env::var("ALACRITTY_SOCKET").is_ok() {
+    if ctx.var("ALACRITTY_SOCKET").is_some() {
         return Some("Alacritty".to_string());
     }
     
     // Check for WezTerm
-    if env::var("WEZTERM_PANE").is_ok() {
+    if ctx.var("WEZTERM_PANE").is_some() {
         return Some("WezTerm".to_string());
     }
     
     // Check for Kitty
-    if env::var("KITTY_WINDOW_ID").is_ok() {
+    if ctx.var("KITTY_WINDOW_ID").is_some() {
         return Some("Kitty".to_string());
     }
     
     None
 }
 
-fn get_parent_pid() -> Option<u32> {
+fn get_ancestor_pids(parent_pid: u32) -> Vec<u32> {
+    // Without /proc, one `ps` lists every parent at once
+    let table = (!Path::new("/proc/self/stat").exists()).then(process_table);
+    let ppid_of = |pid| match table {
+        Some(ref table) => table.get(&pid).copied(),
+        None => proc_ppid(pid),
+    };
+
+    let mut pids = vec![parent_pid];
+    let mut pid = parent_pid;
+
+    // Stop at init or after a reasonable depth
+    for _ in 0..16 {
+        match ppid_of(pid) {
+            Some(ppid) if ppid > 1 => {
+                pids.push(ppid);
+                pid = ppid;
+            }
+            _ => break,
+        }
+    }
+
+    pids
+}
+
+fn proc_ppid(pid: u32) -> Option<u32> {
+    parse_stat_ppid(&fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?)
+}
+
+/// The parent in `/proc/<pid>/stat`: `pid (comm) state ppid ...`, where the
+/// command name may itself hold spaces and parentheses
+fn parse_stat_ppid(stat: &str) -> Option<u32> {
+    stat.rsplit_once(')')?.1.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Every process's parent, by pid
+fn process_table() -> HashMap<u32, u32> {
+    let output = Command::new("ps").args(["-A", "-o", "pid=,ppid="]).output();
+    match output {
+        Ok(output) => parse_process_table(&String::from_utf8_lossy(&output.stdout)),
+        Err(_) => HashMap::new(),
+    }
+}
+
+fn parse_process_table(output: &str) -> HashMap<u32, u32> {
+    output.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().map(str::parse);
+            match (fields.next(), fields.next()) {
+                (Some(Ok(pid)), Some(Ok(ppid))) => Some((pid, ppid)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The device of `pid`'s controlling terminal, if it has one
+fn get_tty_of(pid: u32) -> Option<String> {
     let output = Command::new("ps")
-        .args(&["-p", &std::process::id().to_string(), "-o", "ppid="])
+        .args(["-p", &pid.to_string(), "-o", "tty="])
         .output()
         .ok()?;
-    
-    let ppid_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    ppid_str.parse().ok()
+
+    // `?` (Linux) or `??` (macOS) when there's none
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if name.is_empty() || name.starts_with('?') {
+        return None;
+    }
+    Some(format!("/dev/{}", name))
 }
 
 fn get_process_info(pid: u32) -> Option<String> {
     let output = Command::new("ps")
-        .args(&["-p", &pid.to_string(), "-o", "comm="])
+        .args(["-p", &pid.to_string(), "-o", "comm="])
         .output()
         .ok()?;
     
@@ -192,10 +322,9 @@ pub fn activate_terminal(info: &TerminalInfo) -> Result<(), String> {
     }
 }
 
-#[cfg(target_os = "macos")]
-fn activate_terminal_app(app_name: &str) -> Result<(), String> {
-    // Map our internal names to actual app names for AppleScript
-    let actual_app_name = match app_name {
+/// Map our internal names to actual macOS app names
+pub fn macos_app_name(app_name: &str) -> &str {
+    match app_name {
         "Cursor" => "Cursor",
         "VSCode" => "Visual Studio Code",
         "iTerm2" => "iTerm",
@@ -206,7 +335,12 @@ fn activate_terminal_app(app_name: &str) -> Result<(), String> {
         "Kitty" => "kitty",
         "Hyper" => "Hyper",
         other => other,
-    };
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn activate_terminal_app(app_name: &str) -> Result<(), String> {
+    let actual_app_name = macos_app_name(app_name);
     
     let script = format!(r#"tell application "{}" to activate"#, actual_app_name);
     
@@ -221,4 +355,49 @@ fn activate_terminal_app(app_name: &str) -> Result<(), String> {
     } else {
         Err(format!("Failed to activate {}", app_name))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(not(target_os = "macos"))]
+fn activate_terminal_app(app_name: &str) -> Result<(), String> {
+    // Map our internal names to the X11 window classes used by wmctrl
+    let window_class = match app_name {
+        "Cursor" => "cursor",
+        "VSCode" => "code",
+        "Terminal" => "gnome-terminal",
+        "Alacritty" => "Alacritty",
+        "WezTerm" => "org.wezfurlong.wezterm",
+        "Kitty" => "kitty",
+        "Hyper" => "hyper",
+        other => other,
+    };
+
+    let output = Command::new("wmctrl")
+        .args(["-x", "-a", window_class])
+        .status()
+        .map_err(|e| format!("Failed to run wmctrl: {}", e))?;
+
+    if output.success() {
+        Ok(())
+    } else {
+        Err(format!("Failed to activate {}", app_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parent_pids() {
+        assert_eq!(parse_stat_ppid("4242 (my (odd) cmd) S 4100 4242 4100 34816"), Some(4100));
+        assert_eq!(parse_stat_ppid("garbage"), None);
+
+        let table = parse_process_table("    1     0\n 4100     1\n 4242  4100\n");
+        assert_eq!(table.get(&4242), Some(&4100));
+        assert_eq!(table.len(), 3);
+
+        let ancestors = get_ancestor_pids(std::process::id());
+        assert_eq!(ancestors[0], std::process::id());
+        assert_eq!(ancestors.get(1), Some(&std::os::unix::process::parent_id()).filter(|&&ppid| ppid > 1));
+    }
+}
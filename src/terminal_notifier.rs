@@ -1,27 +1,53 @@
 use std::process::Command;
-use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
 use crate::types::{NotificationData, Config};
+
+#[cfg(all(target_os = "macos", feature = "macos-bundle"))]
+use std::{fs, path::Path};
+#[cfg(all(target_os = "macos", feature = "macos-bundle"))]
 use include_dir::{include_dir, Dir};
 
 // Embed the terminal-notifier.app at compile time
+#[cfg(all(target_os = "macos", feature = "macos-bundle"))]
 static TERMINAL_NOTIFIER_APP: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/resources/terminal-notifier.app");
 
 /// Get the path to terminal-notifier, preferring Homebrew version
 pub fn get_terminal_notifier_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    // First, try to find terminal-notifier in PATH (Homebrew installation)
-    if let Ok(output) = Command::new("which").arg("terminal-notifier").output() {
-        if output.status.success() {
-            if let Ok(path_str) = String::from_utf8(output.stdout) {
-                let path = PathBuf::from(path_str.trim());
-                if path.exists() {
-                    return Ok(path);
-                }
-            }
-        }
+    // First, try to find terminal-notifier in PATH (Homebrew installation).
+    // Looked up once per process, which matters for the daemon.
+    static IN_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+    if let Some(path) = IN_PATH.get_or_init(|| find_in_path("terminal-notifier")) {
+        return Ok(path.clone());
     }
 
     // Fallback to bundled version
+    bundled_terminal_notifier_path()
+}
+
+fn find_in_path(binary: &str) -> Option<PathBuf> {
+    let output = Command::new("which").arg(binary).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let path = PathBuf::from(String::from_utf8(output.stdout).ok()?.trim());
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(all(target_os = "macos", feature = "macos-bundle")))]
+fn bundled_terminal_notifier_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Err("terminal-notifier not found in PATH and no bundled copy is available".into())
+}
+
+/// Get the path to the bundled terminal-notifier, extracting it on first use
+#[cfg(all(target_os = "macos", feature = "macos-bundle"))]
+fn bundled_terminal_notifier_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
     let cache_dir = dirs::cache_dir()
         .or_else(|| dirs::home_dir().map(|h| h.join(".cache")))
         .unwrap_or_else(std::env::temp_dir)
@@ -56,6 +82,7 @@ pub fn get_terminal_notifier_path() -> Result<PathBuf, Box<dyn std::error::Error
 }
 
 /// Extract the bundled terminal-notifier.app to the cache directory
+#[cfg(all(target_os = "macos", feature = "macos-bundle"))]
 fn extract_bundled_app(cache_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
     fs::create_dir_all(cache_dir)?;
 
@@ -85,10 +112,14 @@ pub fn send_notification(
     // Basic notification parameters
     cmd.arg("-title").arg(&data.title)
        .arg("-message").arg(&data.body)
-       .arg("-sound").arg(&data.sound)
        .arg("-sender").arg("com.apple.Terminal")
        .arg("-appIcon").arg("https://www.anthropic.com/favicon.ico");
 
+    // An empty sound means a silent notification
+    if !data.sound.is_empty() {
+        cmd.arg("-sound").arg(&data.sound);
+    }
+
     // Add click action if we have a session ID and click behavior is enabled
     if config.notifications.click_behavior.enabled {
         if let Some(sid) = session_id {
@@ -117,8 +148,43 @@ pub fn send_notification(
     Ok(())
 }
 
+/// Show a notification with an action per entry in `choices` and wait for one
+/// to be picked, returning its index
+pub fn ask(
+    data: &NotificationData,
+    choices: &[&str],
+    timeout: Duration,
+) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(get_terminal_notifier_path()?);
+
+    cmd.arg("-title").arg(&data.title)
+       .arg("-message").arg(&data.body)
+       .arg("-appIcon").arg("https://www.anthropic.com/favicon.ico")
+       .arg("-actions").arg(choices.join(","))
+       .arg("-timeout").arg(timeout.as_secs().max(1).to_string());
+
+    if !data.sound.is_empty() {
+        cmd.arg("-sound").arg(&data.sound);
+    }
+
+    // terminal-notifier blocks while actions are shown and prints the one picked
+    let output = cmd.output()?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("terminal-notifier failed: {}", error).into());
+    }
+
+    Ok(parse_choice(&String::from_utf8_lossy(&output.stdout), choices))
+}
+
+/// terminal-notifier prints the action label, or `@TIMEOUT`, `@CLOSED` and the like
+fn parse_choice(output: &str, choices: &[&str]) -> Option<usize> {
+    let output = output.trim();
+    choices.iter().position(|choice| *choice == output)
+}
+
 /// Get the path to the activate_session binary
-fn get_activate_session_binary() -> Option<String> {
+pub fn get_activate_session_binary() -> Option<String> {
     // First, try to find it in the target directory (development)
     let dev_paths = vec![
         "target/debug/activate_session",
@@ -159,6 +225,7 @@ fn get_activate_session_binary() -> Option<String> {
 }
 
 // Simple dirs module for cache directory
+#[cfg(all(target_os = "macos", feature = "macos-bundle"))]
 mod dirs {
     use std::path::PathBuf;
     
@@ -0,0 +1,370 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A Claude Code hook invocation as received on stdin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "Value")]
+pub struct HookPayload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(flatten)]
+    pub event: HookEvent,
+}
+
+/// The event-specific part of a hook payload, tagged by `hook_event_name`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "hook_event_name")]
+pub enum HookEvent {
+    Notification(NotificationEvent),
+    PreToolUse(PreToolUseEvent),
+    PostToolUse(PostToolUseEvent),
+    UserPromptSubmit(UserPromptSubmitEvent),
+    Stop(StopEvent),
+    SubagentStop(StopEvent),
+    PreCompact(PreCompactEvent),
+    SessionStart(SessionStartEvent),
+    SessionEnd(SessionEndEvent),
+    /// Any event we don't model, kept as the raw JSON
+    #[serde(untagged)]
+    Unknown(Value),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationEvent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Only sent by legacy payloads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PreToolUseEvent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_input: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PostToolUseEvent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_input: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_response: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserPromptSubmitEvent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+/// Shared by `Stop` and `SubagentStop`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StopEvent {
+    /// Whether Claude is already continuing because of a stop hook
+    #[serde(default)]
+    pub stop_hook_active: bool,
+    /// Only sent by legacy payloads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Only sent by legacy payloads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PreCompactEvent {
+    /// "manual" or "auto"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_instructions: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionStartEvent {
+    /// "startup", "resume", "clear" or "compact"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionEndEvent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl HookEvent {
+    /// The hook event name as Claude Code spells it
+    pub fn name(&self) -> &str {
+        match self {
+            HookEvent::Notification(_) => "Notification",
+            HookEvent::PreToolUse(_) => "PreToolUse",
+            HookEvent::PostToolUse(_) => "PostToolUse",
+            HookEvent::UserPromptSubmit(_) => "UserPromptSubmit",
+            HookEvent::Stop(_) => "Stop",
+            HookEvent::SubagentStop(_) => "SubagentStop",
+            HookEvent::PreCompact(_) => "PreCompact",
+            HookEvent::SessionStart(_) => "SessionStart",
+            HookEvent::SessionEnd(_) => "SessionEnd",
+            HookEvent::Unknown(value) => value.get("hook_event_name")
+                .and_then(Value::as_str)
+                .unwrap_or("Unknown"),
+        }
+    }
+
+    /// Key used for this event in per-event config tables, e.g. `pre_tool_use`
+    pub fn config_key(&self) -> String {
+        match self {
+            HookEvent::Unknown(_) => "unknown".to_string(),
+            known => snake_case(known.name()),
+        }
+    }
+
+    /// Whether this is the notice Claude sends after waiting a while for a
+    /// prompt, rather than a request for approval
+    pub fn is_idle_notice(&self) -> bool {
+        matches!(self, HookEvent::Notification(e) if e.message.as_deref().is_some_and(|m| m.contains("waiting for your input")))
+    }
+
+    /// The tool this event is about, if any
+    pub fn tool_name(&self) -> Option<&str> {
+        match self {
+            HookEvent::Notification(e) => e.tool_name.as_deref(),
+            HookEvent::PreToolUse(e) => e.tool_name.as_deref(),
+            HookEvent::PostToolUse(e) => e.tool_name.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The tool input of a `PreToolUse` or `PostToolUse` event
+    pub fn tool_input(&self) -> Option<&Value> {
+        match self {
+            HookEvent::PreToolUse(e) => e.tool_input.as_ref(),
+            HookEvent::PostToolUse(e) => e.tool_input.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The shell command of a Bash tool call
+    pub fn command(&self) -> Option<&str> {
+        self.tool_input()?.get("command")?.as_str()
+    }
+
+    /// The tool input of the built-in tools, picked apart by tool
+    pub fn typed_tool_input(&self) -> ToolInput<'_> {
+        let field = |name: &str| self.tool_input().and_then(|input| input.get(name)).and_then(Value::as_str);
+
+        let typed = match self.tool_name().unwrap_or("") {
+            "Bash" => field("command").map(|command| ToolInput::Bash { command }),
+            "Read" | "Write" | "Edit" | "MultiEdit" => field("file_path").map(|path| ToolInput::File { path }),
+            "NotebookEdit" => field("notebook_path").map(|path| ToolInput::File { path }),
+            "Glob" | "Grep" => field("pattern").map(|pattern| ToolInput::Search { pattern, path: field("path") }),
+            "WebFetch" => field("url").map(|url| ToolInput::WebFetch { url }),
+            _ => None,
+        };
+        typed.unwrap_or(ToolInput::Other)
+    }
+}
+
+/// The `tool_input` of a built-in tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolInput<'a> {
+    Bash { command: &'a str },
+    /// Read, Write, Edit, MultiEdit and NotebookEdit
+    File { path: &'a str },
+    /// Glob and Grep
+    Search { pattern: &'a str, path: Option<&'a str> },
+    WebFetch { url: &'a str },
+    /// Any other tool, or input missing the fields we expect
+    Other,
+}
+
+impl ToolInput<'_> {
+    /// The path the tool works on, if it has one
+    pub fn path(&self) -> Option<&str> {
+        match *self {
+            ToolInput::File { path } => Some(path),
+            ToolInput::Search { path, .. } => path,
+            _ => None,
+        }
+    }
+
+    /// A one-line description of what the tool is about to do
+    pub fn summary(&self) -> &str {
+        match *self {
+            ToolInput::Bash { command } => command,
+            ToolInput::File { path } => path,
+            ToolInput::Search { pattern, .. } => pattern,
+            ToolInput::WebFetch { url } => url,
+            ToolInput::Other => "",
+        }
+    }
+}
+
+impl TryFrom<Value> for HookPayload {
+    type Error = serde_json::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let value = normalize(value);
+        if !value.is_object() {
+            return Err(serde::de::Error::custom("hook payload must be a JSON object"));
+        }
+
+        let field = |name: &str| value.get(name).and_then(Value::as_str).map(String::from);
+        let session_id = field("session_id");
+        let transcript_path = field("transcript_path");
+        let cwd = field("cwd");
+
+        Ok(HookPayload {
+            session_id,
+            transcript_path,
+            cwd,
+            event: serde_json::from_value(value)?,
+        })
+    }
+}
+
+/// Keys accepted in per-event config tables
+pub const EVENT_KEYS: &[&str] = &[
+    "notification", "pre_tool_use", "post_tool_use", "user_prompt_submit", "stop",
+    "subagent_stop", "pre_compact", "session_start", "session_end", "unknown",
+];
+
+/// Config key for an event name, e.g. `pre_tool_use` for `PreToolUse`
+pub fn event_config_key(name: &str) -> String {
+    let key = snake_case(name);
+    if EVENT_KEYS.contains(&key.as_str()) { key } else { "unknown".to_string() }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut key = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            key.push('_');
+        }
+        key.extend(c.to_lowercase());
+    }
+    key
+}
+
+/// Rewrite legacy payload shapes into the current Claude Code format.
+///
+/// Older payloads used `event` instead of `hook_event_name` and nested tool
+/// details under `metadata` or `content` rather than at the top level. Fields
+/// already present at the top level always win.
+pub fn normalize(value: Value) -> Value {
+    let Value::Object(mut map) = value else {
+        return value;
+    };
+
+    if let Some(event) = map.remove("event") {
+        map.entry("hook_event_name").or_insert(event);
+    }
+
+    if let Some(Value::Object(metadata)) = map.remove("metadata") {
+        for (key, value) in metadata {
+            if value.is_null() {
+                continue;
+            }
+            match key.as_str() {
+                "command" => insert_tool_input(&mut map, "command", value),
+                _ => {
+                    map.entry(key).or_insert(value);
+                }
+            }
+        }
+    }
+
+    if let Some(Value::Object(mut content)) = map.remove("content") {
+        if let Some(tool_name) = content.remove("tool_name") {
+            map.entry("tool_name").or_insert(tool_name);
+        }
+        if let Some(parameters) = content.remove("parameters") {
+            map.entry("tool_input").or_insert(parameters);
+        }
+    }
+
+    Value::Object(map)
+}
+
+fn insert_tool_input(map: &mut Map<String, Value>, key: &str, value: Value) {
+    let input = map.entry("tool_input").or_insert_with(|| Value::Object(Map::new()));
+    if let Value::Object(input) = input {
+        input.entry(key).or_insert(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parses_typed_event() {
+        let payload: HookPayload = serde_json::from_value(json!({
+            "hook_event_name": "PreCompact",
+            "session_id": "abc",
+            "cwd": "/tmp/project",
+            "trigger": "manual",
+            "custom_instructions": "keep the plan"
+        })).unwrap();
+
+        assert_eq!(payload.session_id.as_deref(), Some("abc"));
+        assert_eq!(payload.cwd.as_deref(), Some("/tmp/project"));
+        match payload.event {
+            HookEvent::PreCompact(e) => {
+                assert_eq!(e.trigger.as_deref(), Some("manual"));
+                assert_eq!(e.custom_instructions.as_deref(), Some("keep the plan"));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_normalizes_legacy_metadata() {
+        let payload: HookPayload = serde_json::from_value(json!({
+            "event": "PreToolUse",
+            "metadata": { "tool_name": "Bash", "command": "ls", "description": null, "message": null }
+        })).unwrap();
+
+        assert_eq!(payload.event.name(), "PreToolUse");
+        assert_eq!(payload.event.tool_name(), Some("Bash"));
+        assert_eq!(payload.event.command(), Some("ls"));
+    }
+
+    #[test]
+    fn test_normalizes_legacy_content() {
+        let payload: HookPayload = serde_json::from_value(json!({
+            "event": "PreToolUse",
+            "content": { "tool_name": "Bash", "parameters": { "command": "npm test" } }
+        })).unwrap();
+
+        assert_eq!(payload.event.tool_name(), Some("Bash"));
+        assert_eq!(payload.event.command(), Some("npm test"));
+    }
+
+    #[test]
+    fn test_unknown_event_preserves_raw_json() {
+        let raw = json!({
+            "hook_event_name": "SomethingNew",
+            "session_id": "abc",
+            "extra": { "nested": [1, 2, 3] }
+        });
+        let payload: HookPayload = serde_json::from_value(raw.clone()).unwrap();
+
+        assert_eq!(payload.event.name(), "SomethingNew");
+        match payload.event {
+            HookEvent::Unknown(value) => assert_eq!(value, raw),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}
@@ -1,33 +1,31 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct HookPayload {
-    #[serde(alias = "hook_event_name")]
-    pub event: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub content: Option<Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub metadata: Option<Metadata>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_name: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_input: Option<Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub session_id: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub transcript_path: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub cwd: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Metadata {
-    pub tool_name: Option<String>,
-    pub command: Option<String>,
-    pub description: Option<String>,
-    pub message: Option<String>,
-}
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::backends::chat::ChatConfig;
+use crate::backends::gotify::GotifyConfig;
+use crate::backends::matrix::MatrixConfig;
+use crate::backends::mqtt::MqttConfig;
+use crate::backends::ntfy::NtfyConfig;
+use crate::backends::osc::OscConfig;
+use crate::backends::pushover::PushoverConfig;
+use crate::backends::smtp::SmtpConfig;
+use crate::backends::telegram::TelegramConfig;
+use crate::backends::webhook::WebhookConfig;
+use crate::daemon::DaemonConfig;
+use crate::debounce::DebounceConfig;
+use crate::escalation::EscalationConfig;
+use crate::error::BLOCKING_EXIT_CODE;
+use crate::permission::PermissionConfig;
+use crate::policy::PolicyConfig;
+use crate::private_dir;
+use crate::rules::Rule;
+use crate::schedule::ScheduleConfig;
+use crate::template::Template;
+
+mod hook;
+
+pub use hook::*;
 
 #[derive(Debug)]
 pub struct NotificationData {
@@ -36,7 +34,7 @@ pub struct NotificationData {
     pub sound: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Config {
     #[serde(default)]
     pub notifications: NotificationConfig,
@@ -44,15 +42,105 @@ pub struct Config {
     pub testing: TestConfig,
     #[serde(default)]
     pub debug: DebugConfig,
+    #[serde(default)]
+    pub backends: BackendsConfig,
+    /// Ordered filtering rules; the first match decides
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    #[serde(default)]
+    pub hook: HookConfig,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    #[serde(default)]
+    pub permissions: PermissionConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    #[serde(default)]
+    pub escalation: EscalationConfig,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            notifications: NotificationConfig::default(),
-            testing: TestConfig::default(),
-            debug: DebugConfig::default(),
+impl Config {
+    /// Check the parts of the config that serde can't, such as templates
+    pub fn validate(&self) -> Result<(), String> {
+        match self.clone().drop_invalid_sections().into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Replace every section that fails validation with its defaults, so one
+    /// bad value doesn't cost the rest of the config. Returns why each one
+    /// was dropped.
+    pub fn drop_invalid_sections(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        self.notifications.templates.retain(|event, templates| {
+            let result = if EVENT_KEYS.contains(&event.as_str()) {
+                [&templates.title_template, &templates.body_template].into_iter().flatten()
+                    .try_for_each(|source| Template::parse(source).and_then(|template| template.validate_fields()))
+                    .map_err(|e| format!("notifications.templates.{}: {}", event, e))
+            } else {
+                Err(format!("Unknown event in notifications.templates: {}", event))
+            };
+            keep(result, &mut errors)
+        });
+
+        let mut index = 0;
+        self.rules.retain(|rule| {
+            index += 1;
+            keep(rule.validate().map_err(|e| format!("rules[{}]: {}", index - 1, e)), &mut errors)
+        });
+
+        let result = self.schedule.validate().map_err(|e| format!("schedule: {}", e));
+        reset(&mut self.schedule, result, &mut errors);
+        let result = self.policy.validate().map_err(|e| format!("policy.{}", e));
+        reset(&mut self.policy, result, &mut errors);
+        let result = self.permissions.validate().map_err(|e| format!("permissions.{}", e));
+        reset(&mut self.permissions, result, &mut errors);
+        let result = self.escalation.validate().map_err(|e| format!("escalation: {}", e));
+        reset(&mut self.escalation, result, &mut errors);
+
+        let backends = &mut self.backends;
+        let result = backends.webhook.validate().map_err(|e| format!("backends.webhook.body: {}", e));
+        reset(&mut backends.webhook, result, &mut errors);
+        let result = backends.ntfy.validate().map_err(|e| format!("backends.ntfy.{}", e));
+        reset(&mut backends.ntfy, result, &mut errors);
+        let result = backends.slack.validate().map_err(|e| format!("backends.slack.{}", e));
+        reset(&mut backends.slack, result, &mut errors);
+        let result = backends.discord.validate().map_err(|e| format!("backends.discord.{}", e));
+        reset(&mut backends.discord, result, &mut errors);
+        let result = backends.smtp.validate().map_err(|e| format!("backends.smtp: {}", e));
+        reset(&mut backends.smtp, result, &mut errors);
+        let result = backends.pushover.validate().map_err(|e| format!("backends.pushover.{}", e));
+        reset(&mut backends.pushover, result, &mut errors);
+        let result = backends.gotify.validate().map_err(|e| format!("backends.gotify.{}", e));
+        reset(&mut backends.gotify, result, &mut errors);
+        let result = backends.mqtt.validate().map_err(|e| format!("backends.mqtt.{}", e));
+        reset(&mut backends.mqtt, result, &mut errors);
+
+        if self.hook.exit_code_on_error == BLOCKING_EXIT_CODE {
+            errors.push(format!(
+                "hook.exit_code_on_error can't be {}, Claude Code treats it as a blocking result",
+                BLOCKING_EXIT_CODE
+            ));
+            self.hook.exit_code_on_error = 0;
         }
+
+        errors
+    }
+}
+
+/// Whether to keep an entry, recording why not
+fn keep(result: Result<(), String>, errors: &mut Vec<String>) -> bool {
+    result.map_err(|e| errors.push(e)).is_ok()
+}
+
+/// Put a section back to its defaults if it failed validation
+fn reset<T: Default>(section: &mut T, result: Result<(), String>, errors: &mut Vec<String>) {
+    if !keep(result, errors) {
+        *section = T::default();
     }
 }
 
@@ -64,6 +152,26 @@ pub struct NotificationConfig {
     pub sounds: SoundConfig,
     #[serde(default = "default_click_behavior")]
     pub click_behavior: ClickBehavior,
+    #[serde(default = "default_backends")]
+    pub backends: Vec<String>,
+    #[serde(default)]
+    pub events: EventToggles,
+    /// Title/body templates keyed by event, e.g. `pre_tool_use`
+    #[serde(default)]
+    pub templates: BTreeMap<String, EventTemplate>,
+    #[serde(default)]
+    pub debounce: DebounceConfig,
+    /// Per event: whether to notify while the session's terminal is focused
+    #[serde(default)]
+    pub notify_when_focused: EventToggles,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EventTemplate {
+    #[serde(default)]
+    pub title_template: Option<String>,
+    #[serde(default)]
+    pub body_template: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -80,6 +188,11 @@ impl Default for NotificationConfig {
             timeout: default_timeout(),
             sounds: SoundConfig::default(),
             click_behavior: ClickBehavior::default(),
+            backends: default_backends(),
+            events: EventToggles::default(),
+            templates: BTreeMap::new(),
+            debounce: DebounceConfig::default(),
+            notify_when_focused: EventToggles::default(),
         }
     }
 }
@@ -103,6 +216,20 @@ pub struct SoundConfig {
     pub completion: String,
     #[serde(default = "default_unknown_sound")]
     pub unknown: String,
+    #[serde(default = "default_tool_result_sound")]
+    pub tool_result: String,
+    #[serde(default = "default_tool_failure_sound")]
+    pub tool_failure: String,
+    #[serde(default = "default_prompt_submit_sound")]
+    pub prompt_submit: String,
+    #[serde(default = "default_subagent_completion_sound")]
+    pub subagent_completion: String,
+    #[serde(default = "default_compact_sound")]
+    pub compact: String,
+    #[serde(default = "default_session_start_sound")]
+    pub session_start: String,
+    #[serde(default = "default_session_end_sound")]
+    pub session_end: String,
 }
 
 impl Default for SoundConfig {
@@ -112,6 +239,72 @@ impl Default for SoundConfig {
             tool_use: default_tool_sound(),
             completion: default_completion_sound(),
             unknown: default_unknown_sound(),
+            tool_result: default_tool_result_sound(),
+            tool_failure: default_tool_failure_sound(),
+            prompt_submit: default_prompt_submit_sound(),
+            subagent_completion: default_subagent_completion_sound(),
+            compact: default_compact_sound(),
+            session_start: default_session_start_sound(),
+            session_end: default_session_end_sound(),
+        }
+    }
+}
+
+/// Per-event on/off switches, keyed like `pre_tool_use`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventToggles {
+    #[serde(default = "default_event_enabled")]
+    pub notification: bool,
+    #[serde(default = "default_event_enabled")]
+    pub pre_tool_use: bool,
+    #[serde(default = "default_event_enabled")]
+    pub post_tool_use: bool,
+    #[serde(default = "default_event_enabled")]
+    pub user_prompt_submit: bool,
+    #[serde(default = "default_event_enabled")]
+    pub stop: bool,
+    #[serde(default = "default_event_enabled")]
+    pub subagent_stop: bool,
+    #[serde(default = "default_event_enabled")]
+    pub pre_compact: bool,
+    #[serde(default = "default_event_enabled")]
+    pub session_start: bool,
+    #[serde(default = "default_event_enabled")]
+    pub session_end: bool,
+    #[serde(default = "default_event_enabled")]
+    pub unknown: bool,
+}
+
+impl Default for EventToggles {
+    fn default() -> Self {
+        Self {
+            notification: true,
+            pre_tool_use: true,
+            post_tool_use: true,
+            user_prompt_submit: true,
+            stop: true,
+            subagent_stop: true,
+            pre_compact: true,
+            session_start: true,
+            session_end: true,
+            unknown: true,
+        }
+    }
+}
+
+impl EventToggles {
+    pub fn is_enabled(&self, event: &str) -> bool {
+        match event {
+            "Notification" => self.notification,
+            "PreToolUse" => self.pre_tool_use,
+            "PostToolUse" => self.post_tool_use,
+            "UserPromptSubmit" => self.user_prompt_submit,
+            "Stop" => self.stop,
+            "SubagentStop" => self.subagent_stop,
+            "PreCompact" => self.pre_compact,
+            "SessionStart" => self.session_start,
+            "SessionEnd" => self.session_end,
+            _ => self.unknown,
         }
     }
 }
@@ -130,6 +323,94 @@ pub struct DebugConfig {
     pub enabled: bool,
 }
 
+/// How the hook process itself behaves, configured under `[hook]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HookConfig {
+    /// Exit code after a failure; 0 keeps Claude Code quiet
+    #[serde(default)]
+    pub exit_code_on_error: u8,
+    /// Where failures are logged; a file in a private directory if unset,
+    /// disabled if empty
+    #[serde(default)]
+    pub log_file: Option<String>,
+    /// Hand delivery to a detached process so the hook returns immediately
+    #[serde(default)]
+    pub detach: bool,
+    /// Wall time the hook should stay under, reported in debug output
+    #[serde(default = "default_budget_ms")]
+    pub budget_ms: u64,
+}
+
+impl Default for HookConfig {
+    fn default() -> Self {
+        Self {
+            exit_code_on_error: 0,
+            log_file: None,
+            detach: false,
+            budget_ms: default_budget_ms(),
+        }
+    }
+}
+
+impl HookConfig {
+    pub fn log_path(&self) -> Option<PathBuf> {
+        match self.log_file.as_deref() {
+            None => Some(private_dir::default_dir("log").join("claude-notifier.log")),
+            Some("") => None,
+            Some(path) => Some(PathBuf::from(path)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BackendsConfig {
+    #[serde(default)]
+    pub dbus: DbusConfig,
+    #[serde(default)]
+    pub webhook: WebhookConfig,
+    #[serde(default)]
+    pub ntfy: NtfyConfig,
+    #[serde(default)]
+    pub slack: ChatConfig,
+    #[serde(default)]
+    pub discord: ChatConfig,
+    #[serde(default)]
+    pub smtp: SmtpConfig,
+    #[serde(default)]
+    pub telegram: TelegramConfig,
+    #[serde(default)]
+    pub matrix: MatrixConfig,
+    #[serde(default)]
+    pub pushover: PushoverConfig,
+    #[serde(default)]
+    pub gotify: GotifyConfig,
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub osc: OscConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbusConfig {
+    #[serde(default = "default_dbus_app_name")]
+    pub app_name: String,
+    #[serde(default)]
+    pub icon: String,
+    /// Bus address to use instead of the session bus
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
+impl Default for DbusConfig {
+    fn default() -> Self {
+        Self {
+            app_name: default_dbus_app_name(),
+            icon: String::new(),
+            address: None,
+        }
+    }
+}
+
 // Default value functions
 fn default_timeout() -> u32 { 5000 }
 
@@ -141,7 +422,23 @@ fn default_approval_sound() -> String { "Glass".to_string() }
 fn default_tool_sound() -> String { "Pop".to_string() }
 fn default_completion_sound() -> String { "Hero".to_string() }
 fn default_unknown_sound() -> String { "Tink".to_string() }
+fn default_tool_result_sound() -> String { "Morse".to_string() }
+fn default_tool_failure_sound() -> String { "Basso".to_string() }
+fn default_prompt_submit_sound() -> String { "Tink".to_string() }
+fn default_subagent_completion_sound() -> String { "Purr".to_string() }
+fn default_compact_sound() -> String { "Funk".to_string() }
+fn default_session_start_sound() -> String { "Blow".to_string() }
+fn default_session_end_sound() -> String { "Bottle".to_string() }
+fn default_event_enabled() -> bool { true }
 fn default_delay() -> u64 { 1000 }
 fn default_click_behavior() -> ClickBehavior { ClickBehavior::default() }
 fn default_enable_click() -> bool { true }
-fn default_action_label() -> String { "Go to Terminal".to_string() }
\ No newline at end of file
+fn default_action_label() -> String { "Go to Terminal".to_string() }
+fn default_dbus_app_name() -> String { "Claude Notifier".to_string() }
+fn default_budget_ms() -> u64 { 50 }
+
+#[cfg(target_os = "macos")]
+fn default_backends() -> Vec<String> { vec!["terminal-notifier".to_string()] }
+
+#[cfg(not(target_os = "macos"))]
+fn default_backends() -> Vec<String> { vec!["dbus".to_string()] }
\ No newline at end of file